@@ -0,0 +1,308 @@
+use crate::osu_util::check_osu_installation;
+use crate::secrets;
+use crate::servers;
+use crate::shortcuts;
+use crate::store::{AuthDetails, SwitcherStore};
+use crate::switcher::lock::SwitchLock;
+use crate::switcher::read_current_auth;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use color_eyre::eyre::{bail, Context, ContextCompat};
+use color_eyre::Result;
+use ini::Ini;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// The version of the bundle format written by this switcher.
+/// Bundles with a newer version cannot be imported.
+const VERSION: u32 = 1;
+
+/// The first line of bundles that have been encrypted with a passphrase.
+const ENCRYPTED_HEADER: &str = "osu!switcher encrypted bundle v1";
+
+/// All the stored sessions and shortcut servers of an osu! installation, to be moved to
+/// a different installation or machine.
+///
+/// This is an ini file with the version and shortcut servers in the general section,
+/// and one `[username@server]` section per account.
+struct Bundle {
+    accounts: Vec<AuthDetails>,
+    /// The accounts that were last used on their server.
+    last_used: Vec<(String, String)>,
+    shortcuts: Vec<String>,
+}
+
+impl Bundle {
+    fn to_ini(&self) -> Ini {
+        let mut ini = Ini::new();
+        ini.with_general_section()
+            .set("Version", VERSION.to_string())
+            .set("Shortcuts", self.shortcuts.join(","));
+
+        for auth in &self.accounts {
+            let last_used = self
+                .last_used
+                .iter()
+                .any(|(server, username)| *server == auth.server && *username == auth.username);
+
+            let section = SwitcherStore::account_section(&*auth.server, &*auth.username);
+            ini.with_section(Some(section))
+                .set("Username", &*auth.username)
+                .set("Server", &*auth.server)
+                .set("Password", &*auth.password)
+                .set("LastUsed", last_used.to_string());
+        }
+
+        ini
+    }
+
+    fn from_ini(ini: &Ini) -> Result<Self> {
+        let general = ini.general_section();
+        let version: u32 = general
+            .get("Version")
+            .context("bundle is missing its version")?
+            .parse()
+            .context("bundle has an invalid version")?;
+
+        if version > VERSION {
+            bail!("this bundle was made by a newer version of osu!switcher, please update first");
+        }
+
+        let shortcuts = general
+            .get("Shortcuts")
+            .unwrap_or("")
+            .split(',')
+            .filter(|server| !server.is_empty())
+            .map(servers::canonicalize)
+            .collect();
+
+        let mut accounts = Vec::new();
+        let mut last_used = Vec::new();
+        for (name, section) in ini.iter() {
+            if name.is_none() {
+                continue;
+            }
+
+            let username = section.get("Username").context("bundle account missing username")?;
+            let server = section.get("Server").context("bundle account missing server")?;
+            let auth = AuthDetails {
+                username: username.to_owned(),
+                password: section.get("Password").unwrap_or("").to_owned(),
+                server: servers::canonicalize(server),
+            };
+
+            if section.get("LastUsed") == Some("true") {
+                last_used.push((auth.server.clone(), auth.username.clone()));
+            }
+            accounts.push(auth);
+        }
+
+        Ok(Self {
+            accounts,
+            last_used,
+            shortcuts,
+        })
+    }
+}
+
+/// Exports all stored sessions (including the one osu! is currently signed in to) and the servers
+/// that shortcuts were created for in this osu! directory into a bundle file, optionally encrypted
+/// with a passphrase.
+pub fn export(osu_dir: &Path, output: &Path, passphrase: Option<&str>) -> Result<()> {
+    let store = SwitcherStore::load(osu_dir)?;
+    let mut accounts = store.accounts();
+    let mut last_used: Vec<(String, String)> = accounts
+        .iter()
+        .filter(|auth| store.last_account(&*auth.server) == Some(&*auth.username))
+        .map(|auth| (auth.server.clone(), auth.username.clone()))
+        .collect();
+
+    // The current session only gets stored once switching away from it
+    if let Some(current) = read_current_auth(osu_dir)?
+        && !current.username.is_empty()
+    {
+        accounts.retain(|auth| auth.server != current.server || auth.username != current.username);
+        last_used.retain(|(server, _)| *server != current.server);
+        last_used.push((current.server.clone(), current.username.clone()));
+        accounts.push(current);
+    }
+
+    let shortcuts = shortcuts::created_servers(osu_dir).unwrap_or_else(|err| {
+        println!("Could not find existing shortcuts, none will be exported: {err:#}");
+        Vec::new()
+    });
+
+    let bundle = Bundle {
+        accounts,
+        last_used,
+        shortcuts,
+    };
+
+    let mut contents = Vec::new();
+    bundle.to_ini().write_to(&mut contents)?;
+
+    let contents = match passphrase {
+        Some(passphrase) => encrypt_bundle(&*contents, passphrase)?,
+        None => {
+            println!("Warning: this bundle is not encrypted, its session keys are in plain text!");
+            contents
+        }
+    };
+
+    fs::write(output, contents).with_context(|| format!("failed to write bundle {output:?}"))?;
+
+    println!(
+        "Exported {0} session(s) and {1} shortcut(s) to {output:?}",
+        bundle.accounts.len(),
+        bundle.shortcuts.len()
+    );
+    Ok(())
+}
+
+/// Imports the sessions from a bundle into the switcher config of an osu! installation, and
+/// recreates the shortcuts it lists. Imported sessions replace stored ones for the same account.
+pub fn import(
+    osu_dir: &Path,
+    input: &Path,
+    passphrase: Option<&str>,
+    create_shortcuts: bool,
+) -> Result<()> {
+    if !check_osu_installation(osu_dir) {
+        bail!("{osu_dir:?} is not an osu! installation");
+    }
+
+    let contents = fs::read(input).with_context(|| format!("failed to read bundle {input:?}"))?;
+    let contents = if contents.starts_with(ENCRYPTED_HEADER.as_bytes()) {
+        let passphrase = match passphrase {
+            Some(passphrase) => passphrase.to_owned(),
+            None => prompt_passphrase()?,
+        };
+        decrypt_bundle(&*contents, &*passphrase)?
+    } else {
+        contents
+    };
+
+    let contents = String::from_utf8(contents).context("corrupted bundle")?;
+    let ini = Ini::load_from_str(&*contents).context("corrupted bundle")?;
+    let bundle = Bundle::from_ini(&ini)?;
+
+    let _lock = SwitchLock::acquire(osu_dir)?;
+    let mut store = SwitcherStore::load(osu_dir)?;
+
+    for auth in &bundle.accounts {
+        store.put(auth);
+        println!("Imported {0}@{1}", auth.username, auth.server);
+    }
+    for (server, username) in &bundle.last_used {
+        store.set_last_account(server, username);
+    }
+
+    store.save()?;
+    store.verify()?;
+
+    if create_shortcuts && !bundle.shortcuts.is_empty() {
+        let servers = bundle.shortcuts.iter().map(|server| &**server);
+        shortcuts::install(osu_dir, servers, false, false)?;
+        println!("Created shortcuts for {0}", bundle.shortcuts.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Encrypts the contents of a bundle with a key derived from a passphrase.
+fn encrypt_bundle(contents: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+
+    let key = secrets::derive_key(passphrase, &salt)?;
+    let data = [&salt, &*secrets::encrypt_bytes(&key, contents)?].concat();
+
+    Ok(format!("{ENCRYPTED_HEADER}\n{0}\n", BASE64_STANDARD.encode(data)).into_bytes())
+}
+
+/// Decrypts the contents of a bundle that was encrypted with [encrypt_bundle].
+fn decrypt_bundle(contents: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let data = String::from_utf8_lossy(&contents[ENCRYPTED_HEADER.len()..]);
+    let data = BASE64_STANDARD
+        .decode(data.trim())
+        .context("corrupted encrypted bundle")?;
+
+    if data.len() < 16 {
+        bail!("corrupted encrypted bundle");
+    }
+
+    let (salt, data) = data.split_at(16);
+    let key = secrets::derive_key(passphrase, salt)?;
+    secrets::decrypt_bytes(&key, data).context("wrong passphrase or corrupted bundle")
+}
+
+/// Asks the user for the passphrase of an encrypted bundle.
+fn prompt_passphrase() -> Result<String> {
+    print!("This bundle is encrypted. Enter its passphrase: ");
+    std::io::stdout().flush()?;
+
+    let mut passphrase = String::new();
+    std::io::stdin().read_line(&mut passphrase)?;
+
+    Ok(passphrase.trim_end_matches(['\r', '\n']).to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_bundles() {
+        let bundle = Bundle {
+            accounts: vec![
+                AuthDetails {
+                    username: "[Toy]".to_owned(),
+                    password: "toykey".to_owned(),
+                    server: "akatsuki.gg".to_owned(),
+                },
+                AuthDetails {
+                    username: "alice".to_owned(),
+                    password: "alicekey".to_owned(),
+                    server: "akatsuki.gg".to_owned(),
+                },
+            ],
+            last_used: vec![("akatsuki.gg".to_owned(), "[Toy]".to_owned())],
+            shortcuts: vec!["akatsuki.gg".to_owned(), "ripple.moe".to_owned()],
+        };
+
+        let mut contents = Vec::new();
+        bundle.to_ini().write_to(&mut contents).unwrap();
+        let ini = Ini::load_from_str(&*String::from_utf8(contents).unwrap()).unwrap();
+        let read = Bundle::from_ini(&ini).unwrap();
+
+        assert_eq!(read.accounts, bundle.accounts);
+        assert_eq!(read.last_used, bundle.last_used);
+        assert_eq!(read.shortcuts, bundle.shortcuts);
+    }
+
+    #[test]
+    fn rejects_newer_bundles() {
+        let ini = Ini::load_from_str(&*format!("Version={0}\n", VERSION + 1)).unwrap();
+        assert!(Bundle::from_ini(&ini).is_err());
+    }
+
+    #[test]
+    fn round_trips_encrypted_bundles() {
+        let contents = b"Version=1\n[alice@akatsuki.gg]\nPassword=alicekey\n";
+        let encrypted = encrypt_bundle(contents, "passphrase").unwrap();
+
+        assert!(encrypted.starts_with(ENCRYPTED_HEADER.as_bytes()));
+        assert!(!String::from_utf8_lossy(&*encrypted).contains("alicekey"));
+        assert_eq!(decrypt_bundle(&*encrypted, "passphrase").unwrap(), contents);
+    }
+
+    #[test]
+    fn rejects_wrong_passphrase() {
+        let encrypted = encrypt_bundle(b"Version=1\n", "passphrase").unwrap();
+
+        assert!(decrypt_bundle(&*encrypted, "another passphrase").is_err());
+        assert!(decrypt_bundle(ENCRYPTED_HEADER.as_bytes(), "passphrase").is_err());
+    }
+}
@@ -0,0 +1,239 @@
+use crate::store::AuthDetails;
+use chrono::{DateTime, Local};
+use color_eyre::eyre::Context;
+use color_eyre::Result;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+/// The file name of the append-only switch journal inside the osu! directory.
+pub const FILE_NAME: &str = "osu!switcher.history";
+
+/// How a switch ended.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Outcome {
+    /// The switch was completed.
+    Switched,
+    /// Nothing had to be switched, for the specified reason.
+    Skipped(String),
+    /// The switch failed with the specified error.
+    Failed(String),
+}
+
+/// A record of a single switch between servers.
+/// These are stored as tab-separated lines in the journal.
+#[derive(Debug)]
+pub struct HistoryEntry {
+    pub timestamp: DateTime<Local>,
+    /// The account that was signed in before switching, if it could be read.
+    pub from: Option<AuthDetails>,
+    /// The account that was switched to, if switching got that far.
+    pub to: Option<AuthDetails>,
+    pub outcome: Outcome,
+}
+
+impl HistoryEntry {
+    /// Starts recording a new switch.
+    pub fn new() -> Self {
+        Self {
+            timestamp: Local::now(),
+            from: None,
+            to: None,
+            outcome: Outcome::Switched,
+        }
+    }
+
+    fn fmt_account(account: &Option<AuthDetails>) -> String {
+        match account {
+            Some(AuthDetails { username, server, .. }) if username.is_empty() => {
+                format!("(signed out)@{server}")
+            }
+            Some(AuthDetails { username, server, .. }) => format!("{username}@{server}"),
+            None => "(unknown)".to_owned(),
+        }
+    }
+
+    fn to_line(&self) -> String {
+        let fields = [
+            self.timestamp.to_rfc3339(),
+            self.from.as_ref().map_or("", |auth| &*auth.server).to_owned(),
+            self.from.as_ref().map_or("", |auth| &*auth.username).to_owned(),
+            self.to.as_ref().map_or("", |auth| &*auth.server).to_owned(),
+            self.to.as_ref().map_or("", |auth| &*auth.username).to_owned(),
+            match self.outcome {
+                Outcome::Switched => "ok",
+                Outcome::Skipped(_) => "skipped",
+                Outcome::Failed(_) => "failed",
+            }
+            .to_owned(),
+            match &self.outcome {
+                Outcome::Switched => String::new(),
+                Outcome::Skipped(detail) | Outcome::Failed(detail) => detail.clone(),
+            },
+        ];
+
+        // Tabs and newlines would break up the entry
+        let fields = fields.map(|field| field.replace(['\t', '\r', '\n'], " "));
+        fields.join("\t")
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [timestamp, from_server, from_user, to_server, to_user, outcome, detail] = *fields
+        else {
+            return None;
+        };
+
+        let account = |server: &str, username: &str| {
+            (!server.is_empty()).then(|| AuthDetails {
+                username: username.to_owned(),
+                password: String::new(),
+                server: server.to_owned(),
+            })
+        };
+
+        Some(Self {
+            timestamp: DateTime::parse_from_rfc3339(timestamp).ok()?.with_timezone(&Local),
+            from: account(from_server, from_user),
+            to: account(to_server, to_user),
+            outcome: match outcome {
+                "ok" => Outcome::Switched,
+                "skipped" => Outcome::Skipped(detail.to_owned()),
+                _ => Outcome::Failed(detail.to_owned()),
+            },
+        })
+    }
+}
+
+/// Appends an entry to the switch journal of an osu! installation.
+pub fn append(osu_dir: &Path, entry: &HistoryEntry) -> Result<()> {
+    let path = osu_dir.join(FILE_NAME);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&*path)
+        .with_context(|| format!("failed to open switch history {path:?}"))?;
+
+    writeln!(file, "{}", entry.to_line())
+        .with_context(|| format!("failed to write switch history {path:?}"))
+}
+
+/// Reads every entry from the switch journal of an osu! installation, oldest first.
+pub fn read(osu_dir: &Path) -> Result<Vec<HistoryEntry>> {
+    let path = osu_dir.join(FILE_NAME);
+
+    if !fs::exists(&*path).unwrap_or(false) {
+        return Ok(Vec::new());
+    }
+
+    let journal = fs::read_to_string(&*path)
+        .with_context(|| format!("failed to read switch history {path:?}"))?;
+
+    Ok(journal.lines().filter_map(HistoryEntry::from_line).collect())
+}
+
+/// Prints the most recent entries of the switch journal.
+pub fn print(osu_dir: &Path, limit: usize) -> Result<()> {
+    let entries = read(osu_dir)?;
+
+    if entries.is_empty() {
+        println!("No switches have been recorded for this osu! installation yet.");
+        return Ok(());
+    }
+
+    for entry in entries.iter().skip(entries.len().saturating_sub(limit)) {
+        println!(
+            "{0}  {1} -> {2}  {3}",
+            entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            HistoryEntry::fmt_account(&entry.from),
+            HistoryEntry::fmt_account(&entry.to),
+            match entry.outcome {
+                Outcome::Switched => "ok",
+                Outcome::Skipped(_) => "skipped",
+                Outcome::Failed(_) => "FAILED",
+            },
+        );
+
+        if let Outcome::Skipped(detail) | Outcome::Failed(detail) = &entry.outcome {
+            println!("    {detail}");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempOsuDir;
+
+    fn auth(username: &str, server: &str) -> Option<AuthDetails> {
+        Some(AuthDetails {
+            username: username.to_owned(),
+            password: String::new(),
+            server: server.to_owned(),
+        })
+    }
+
+    #[test]
+    fn round_trips_entries() {
+        let entry = HistoryEntry {
+            from: auth("alice", "ripple.moe"),
+            to: auth("", "akatsuki.gg"),
+            outcome: Outcome::Skipped("osu! is already running".to_owned()),
+            ..HistoryEntry::new()
+        };
+
+        let read = HistoryEntry::from_line(&*entry.to_line()).unwrap();
+        assert_eq!(read.timestamp, entry.timestamp);
+        assert_eq!(read.from, entry.from);
+        assert_eq!(read.to, entry.to);
+        assert_eq!(read.outcome, entry.outcome);
+
+        let unknown = HistoryEntry::from_line(&*HistoryEntry::new().to_line()).unwrap();
+        assert_eq!((unknown.from, unknown.to), (None, None));
+        assert_eq!(unknown.outcome, Outcome::Switched);
+    }
+
+    #[test]
+    fn escapes_tabs_and_newlines() {
+        let entry = HistoryEntry {
+            from: auth("al\tice", "akatsuki.gg"),
+            outcome: Outcome::Failed("failed to write\tosu!.db:\r\naccess denied".to_owned()),
+            ..HistoryEntry::new()
+        };
+
+        let line = entry.to_line();
+        assert_eq!(line.matches('\t').count(), 6);
+        assert!(!line.contains(['\r', '\n']));
+
+        let read = HistoryEntry::from_line(&*line).unwrap();
+        assert_eq!(read.from.unwrap().username, "al ice");
+        assert_eq!(
+            read.outcome,
+            Outcome::Failed("failed to write osu!.db:  access denied".to_owned())
+        );
+    }
+
+    #[test]
+    fn appends_to_journal() {
+        let osu_dir = TempOsuDir::new("history");
+        assert!(read(&*osu_dir).unwrap().is_empty());
+
+        append(&*osu_dir, &HistoryEntry::new()).unwrap();
+        let failed = HistoryEntry {
+            outcome: Outcome::Failed("locked".to_owned()),
+            ..HistoryEntry::new()
+        };
+        append(&*osu_dir, &failed).unwrap();
+
+        // Lines that can't be parsed are skipped
+        let mut file = OpenOptions::new().append(true).open(osu_dir.join(FILE_NAME)).unwrap();
+        writeln!(file, "not an entry").unwrap();
+
+        let outcomes: Vec<Outcome> =
+            read(&*osu_dir).unwrap().into_iter().map(|entry| entry.outcome).collect();
+        assert_eq!(outcomes, [Outcome::Switched, Outcome::Failed("locked".to_owned())]);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::platform;
+use crate::servers;
+use color_eyre::eyre::Context;
+use color_eyre::Result;
+use std::env;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// How osu! gets launched on this system.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Launcher {
+    /// Starts osu! directly, which requires running on Windows (or inside of Wine).
+    Native,
+    /// Starts osu! through Wine, from a system where osu! can't run natively.
+    Wine(Wine),
+}
+
+impl Launcher {
+    /// Picks the launcher for the current system, which is Wine when running natively on Linux.
+    pub fn detect(wine: Wine) -> Self {
+        if cfg!(target_os = "linux") {
+            Self::Wine(wine)
+        } else {
+            Self::Native
+        }
+    }
+
+    /// Resolves a path given on the command line, which might be a Windows path under Wine.
+    pub fn resolve_path(&self, path: &str) -> PathBuf {
+        match self {
+            Self::Native => PathBuf::from(path),
+            Self::Wine(wine) => {
+                let path = to_unix_path(&*wine.prefix, path);
+                // Resolves the links of the drives back into the actual directories
+                fs::canonicalize(&*path).unwrap_or(path)
+            }
+        }
+    }
+
+    /// Launches osu! with a specified server.
+    pub fn launch(&self, osu_exe: &Path, server: &str) -> Result<()> {
+        match self {
+            Self::Native => platform::launch_osu(osu_exe, server),
+            Self::Wine(wine) => wine.launch(osu_exe, server),
+        }
+    }
+}
+
+impl Default for Launcher {
+    fn default() -> Self {
+        Self::detect(Wine::default())
+    }
+}
+
+impl Display for Launcher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Native => f.write_str("natively"),
+            Self::Wine(wine) => write!(f, "with {0:?} in prefix {1:?}", wine.binary, wine.prefix),
+        }
+    }
+}
+
+/// A Wine installation and the prefix that osu! is installed into.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Wine {
+    /// The wine binary to start osu! with.
+    pub binary: String,
+    /// The Wine prefix (`WINEPREFIX`) to start osu! in.
+    pub prefix: PathBuf,
+}
+
+impl Wine {
+    /// Launches osu! with a specified server through Wine.
+    fn launch(&self, osu_exe: &Path, server: &str) -> Result<()> {
+        let mut command = Command::new(&*self.binary);
+        command.env("WINEPREFIX", &*self.prefix).arg(osu_exe);
+
+        // osu! connects to Bancho without -devserver, so don't pass an empty one through Wine
+        let endpoint = servers::endpoint(server);
+        if !endpoint.is_empty() {
+            command.args(["-devserver", &*endpoint]);
+        }
+        if let Some(osu_dir) = osu_exe.parent() {
+            command.current_dir(osu_dir);
+        }
+
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("failed to start osu! with {0:?}", self.binary))?;
+
+        Ok(())
+    }
+}
+
+impl Default for Wine {
+    /// Follows the `WINE` (like winetricks) and `WINEPREFIX` environment variables.
+    fn default() -> Self {
+        let var = |name| env::var(name).ok().filter(|value| !value.is_empty());
+
+        Self {
+            binary: var("WINE").unwrap_or_else(|| "wine".to_owned()),
+            prefix: default_prefix(var("WINEPREFIX").as_deref(), var("HOME").as_deref()),
+        }
+    }
+}
+
+/// Returns the prefix Wine uses given the values of its `WINEPREFIX` and `HOME` variables.
+pub fn default_prefix(wine_prefix: Option<&str>, home: Option<&str>) -> PathBuf {
+    match wine_prefix {
+        Some(wine_prefix) => PathBuf::from(wine_prefix),
+        None => Path::new(home.unwrap_or("/")).join(".wine"),
+    }
+}
+
+/// Finds the Wine prefix that an osu! directory is installed in, which is the parent of the
+/// `drive_c` directory it is in. Installations outside of a prefix (such as on the z: drive) fall
+/// back to the value of the `WINEPREFIX` variable, if it is set.
+pub fn find_prefix(osu_dir: &Path, wine_prefix: Option<&str>) -> Option<PathBuf> {
+    let drive_c = osu_dir
+        .ancestors()
+        .find(|dir| dir.file_name().is_some_and(|name| name == "drive_c"));
+
+    match drive_c.and_then(Path::parent) {
+        Some(prefix) => Some(prefix.to_owned()),
+        None => wine_prefix.filter(|prefix| !prefix.is_empty()).map(PathBuf::from),
+    }
+}
+
+/// Translates a Windows path (such as `C:\osu!`) into the Unix path it maps to in a Wine prefix.
+/// Any other path is returned as is.
+pub fn to_unix_path(prefix: &Path, path: &str) -> PathBuf {
+    let Some((drive, rest)) = path.split_once(':') else { return PathBuf::from(path) };
+
+    let is_windows_path = drive.len() == 1
+        && drive.as_bytes()[0].is_ascii_alphabetic()
+        && (rest.is_empty() || rest.starts_with(['\\', '/']));
+    if !is_windows_path {
+        return PathBuf::from(path);
+    }
+
+    // Every drive is a link in dosdevices, such as z: linking to the root directory
+    let mut unix_path = prefix
+        .join("dosdevices")
+        .join(format!("{0}:", drive.to_ascii_lowercase()));
+    unix_path.extend(rest.split(['\\', '/']).filter(|part| !part.is_empty()));
+    unix_path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_windows_paths() {
+        let prefix = Path::new("/home/tester/.wine");
+
+        assert_eq!(
+            to_unix_path(prefix, r"C:\Program Files\osu!"),
+            prefix.join("dosdevices/c:/Program Files/osu!")
+        );
+        assert_eq!(
+            to_unix_path(prefix, r"z:\home\tester\osu!\"),
+            prefix.join("dosdevices/z:/home/tester/osu!")
+        );
+        assert_eq!(to_unix_path(prefix, "D:"), prefix.join("dosdevices/d:"));
+    }
+
+    #[test]
+    fn keeps_unix_paths() {
+        let prefix = Path::new("/home/tester/.wine");
+
+        assert_eq!(to_unix_path(prefix, "/games/osu!"), PathBuf::from("/games/osu!"));
+        assert_eq!(to_unix_path(prefix, "osu:lazer"), PathBuf::from("osu:lazer"));
+    }
+
+    #[test]
+    fn finds_default_prefix() {
+        assert_eq!(
+            default_prefix(None, Some("/home/tester")),
+            PathBuf::from("/home/tester/.wine")
+        );
+        assert_eq!(
+            default_prefix(Some("/games/osu-prefix"), Some("/home/tester")),
+            PathBuf::from("/games/osu-prefix")
+        );
+    }
+
+    #[test]
+    fn finds_prefix_of_osu_dir() {
+        assert_eq!(
+            find_prefix(Path::new("/games/osu-prefix/drive_c/osu!"), Some("/home/tester/.wine")),
+            Some(PathBuf::from("/games/osu-prefix"))
+        );
+        assert_eq!(
+            find_prefix(Path::new("/home/tester/osu!"), Some("/games/osu-prefix")),
+            Some(PathBuf::from("/games/osu-prefix"))
+        );
+        assert_eq!(find_prefix(Path::new("/home/tester/osu!"), Some("")), None);
+    }
+}
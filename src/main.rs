@@ -1,39 +1,191 @@
+// `&*` is used throughout to borrow owned values as their borrowed types explicitly
+#![allow(clippy::explicit_auto_deref)]
+
+use crate::launcher::{Launcher, Wine};
+use crate::switcher::repair::RepairPolicy;
+use crate::switcher::SwitchOptions;
 use crate::tui::start_tui;
 use seahorse::{ActionError, ActionResult, App, Command, Context, Flag, FlagType};
+use std::path::{Path, PathBuf};
 use std::{env, panic};
 
+mod bundle;
+mod history;
+mod launcher;
 mod osu_util;
+mod platform;
+mod process;
+mod relocate;
+mod secrets;
+mod servers;
 mod shortcuts;
+mod store;
 mod switcher;
+#[cfg(test)]
+mod test_util;
 mod tui;
+mod uninstall;
 
 fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
 
     let osu_flag = Flag::new("osu", FlagType::String).description("osu! game directory path");
     let server_flag = Flag::new("server", FlagType::String)
-        .description("The target server address (optional). ex: --server akatsuki.pw");
+        .description("The target server address (optional). ex: --server akatsuki.gg");
+    let account_flag = Flag::new("account", FlagType::String)
+        .description("The account to use on the target server (optional). ex: --account tester2");
+    let dry_run_flag = Flag::new("dry-run", FlagType::Bool)
+        .description("Print every change the switch would make without changing anything");
+    let on_repair_flag = Flag::new("on-repair", FlagType::String)
+        .description("What to do if osu! has a pending repair: launch, repair or ask (optional)");
+    let launcher_flag = Flag::new("launcher", FlagType::String)
+        .description("How to start osu!: native or wine (optional, wine by default on Linux)");
+    let wine_flag = Flag::new("wine", FlagType::String)
+        .description("The wine binary to start osu! with (optional, $WINE or wine by default)");
+    let wine_prefix_flag = Flag::new("wine-prefix", FlagType::String)
+        .description("The Wine prefix osu! is installed in (optional, $WINEPREFIX by default)");
+    let kill_all_flag = Flag::new("kill-all", FlagType::Bool)
+        .description("Stop every running osu!, not just the one running from the --osu directory");
+    let shortcut_format_flag = Flag::new("shortcut-format", FlagType::Uint)
+        .description("The argument format of the shortcut starting the switch (set by shortcuts)");
 
     let switch_cmd = Command::new("switch")
         .description("Switch to a different server account")
-        .usage("osu-switcher.exe switch --osu <OSU_DIR> --server <SERVER_ADDRESS>")
+        .usage("osu-switcher.exe switch --osu <OSU_DIR> --server <SERVER> [--account <USERNAME>]")
         .flag(server_flag)
+        .flag(account_flag.clone())
+        .flag(dry_run_flag)
+        .flag(on_repair_flag)
+        .flag(kill_all_flag)
+        .flag(launcher_flag)
+        .flag(wine_flag)
+        .flag(wine_prefix_flag)
+        .flag(shortcut_format_flag)
         .flag(osu_flag.clone())
         .action_with_result(switch);
 
+    let portable_flag = Flag::new("portable", FlagType::Bool)
+        .description("Install the switcher into the osu! directory, and keep all state next to it");
+
     let configure_cmd = Command::new("configure")
         .description("Create desktop shortcuts for servers")
-        .usage("osu-switcher.exe switch --osu <OSU_DIR>")
-        .action(|_| start_tui());
+        .usage("osu-switcher.exe configure [--portable]")
+        .flag(portable_flag)
+        .action(|ctx| start_tui(ctx.bool_flag("portable")));
+
+    let limit_flag = Flag::new("limit", FlagType::Uint)
+        .description("The amount of most recent switches to print (optional, default 20)");
+
+    let history_cmd = Command::new("history")
+        .description("Print the most recent switches made for an osu! installation")
+        .usage("osu-switcher.exe history --osu <OSU_DIR> [--limit <COUNT>]")
+        .flag(osu_flag.clone())
+        .flag(limit_flag)
+        .action_with_result(history);
+
+    let status_cmd = Command::new("status")
+        .description("Show the current server and account, along with all stored sessions")
+        .usage("osu-switcher.exe status --osu <OSU_DIR>")
+        .flag(osu_flag.clone())
+        .action_with_result(status);
+
+    let yes_flag = Flag::new("yes", FlagType::Bool)
+        .description("Skip confirming changes to the server osu! is currently signed in to");
+
+    let accounts_list_cmd = Command::new("list")
+        .description("List all stored sessions")
+        .usage("osu-switcher.exe accounts list --osu <OSU_DIR>")
+        .flag(osu_flag.clone())
+        .action_with_result(accounts_list);
+
+    let accounts_forget_cmd = Command::new("forget")
+        .description("Remove the stored sessions of a server")
+        .usage("osu-switcher.exe accounts forget --osu <OSU_DIR> <SERVER> [--account <USERNAME>]")
+        .flag(osu_flag.clone())
+        .flag(account_flag.clone())
+        .flag(yes_flag.clone())
+        .action_with_result(accounts_forget);
+
+    let accounts_move_cmd = Command::new("move")
+        .description("Move the stored sessions of a server to a new domain")
+        .usage("osu-switcher.exe accounts move --osu <OSU_DIR> <OLD_SERVER> <NEW_SERVER>")
+        .flag(osu_flag.clone())
+        .flag(yes_flag)
+        .action_with_result(accounts_move);
+
+    let accounts_cmd = Command::new("accounts")
+        .description("Manage the sessions stored by the switcher")
+        .usage("osu-switcher.exe accounts <list|forget|move> --osu <OSU_DIR> [...args]")
+        .command(accounts_list_cmd)
+        .command(accounts_forget_cmd)
+        .command(accounts_move_cmd);
+
+    let passphrase_flag = Flag::new("passphrase", FlagType::String)
+        .description("The passphrase to encrypt or decrypt the bundle with (optional)");
+    let no_shortcuts_flag = Flag::new("no-shortcuts", FlagType::Bool)
+        .description("Don't recreate the shortcuts listed in the bundle");
+
+    let export_cmd = Command::new("export")
+        .description("Export all stored sessions and shortcut servers into a bundle file")
+        .usage("osu-switcher.exe export --osu <OSU_DIR> <BUNDLE_FILE> [--passphrase <PASSPHRASE>]")
+        .flag(osu_flag.clone())
+        .flag(passphrase_flag.clone())
+        .action_with_result(export);
+
+    let import_cmd = Command::new("import")
+        .description("Import the stored sessions and shortcuts from a bundle file")
+        .usage("osu-switcher.exe import --osu <OSU_DIR> <BUNDLE_FILE> [--passphrase <PASSPHRASE>]")
+        .flag(osu_flag.clone())
+        .flag(passphrase_flag)
+        .flag(no_shortcuts_flag)
+        .action_with_result(import);
+
+    let settings_cmd = Command::new("settings")
+        .description("List the switcher settings of an osu! installation, or change one of them")
+        .usage("osu-switcher.exe settings --osu <OSU_DIR> [<NAME> <VALUE>]")
+        .flag(osu_flag.clone())
+        .action_with_result(settings);
+
+    let purge_flag = Flag::new("purge", FlagType::Bool).description(
+        "Also delete the stored sessions, the switch history and the encryption key, which makes \
+        the sessions stored for other osu! installations unreadable",
+    );
+
+    let uninstall_cmd = Command::new("uninstall")
+        .description("Delete the installed switcher, its shortcuts and the server icons")
+        .usage("osu-switcher.exe uninstall --osu <OSU_DIR> [--purge]")
+        .flag(osu_flag.clone())
+        .flag(purge_flag)
+        .action_with_result(uninstall);
+
+    let from_flag = Flag::new("from", FlagType::String)
+        .description("The osu! game directory path the shortcuts were created for");
+    let to_flag = Flag::new("to", FlagType::String)
+        .description("The osu! game directory path that osu! was moved to");
+
+    let relocate_cmd = Command::new("relocate")
+        .description("Move the switcher config and shortcuts over to a moved osu! installation")
+        .usage("osu-switcher.exe relocate --from <OLD_OSU_DIR> --to <NEW_OSU_DIR>")
+        .flag(from_flag)
+        .flag(to_flag)
+        .action_with_result(relocate);
 
     let app = App::new(env!("CARGO_PKG_NAME"))
         .description(env!("CARGO_PKG_DESCRIPTION"))
         .author(env!("CARGO_PKG_AUTHORS"))
         .version(env!("CARGO_PKG_VERSION"))
         .usage("osu-switcher.exe <command> [...args]")
-        .action(|_| start_tui())
+        .action(|_| start_tui(false))
         .command(switch_cmd)
-        .command(configure_cmd);
+        .command(configure_cmd)
+        .command(history_cmd)
+        .command(status_cmd)
+        .command(accounts_cmd)
+        .command(export_cmd)
+        .command(import_cmd)
+        .command(settings_cmd)
+        .command(relocate_cmd)
+        .command(uninstall_cmd);
 
     let app_result = panic::catch_unwind(|| app.run(env::args().collect()));
 
@@ -49,9 +201,11 @@ fn main() -> color_eyre::Result<()> {
 }
 
 fn switch(ctx: &Context) -> ActionResult {
-    let osu_dir = match ctx.string_flag("osu") {
-        Ok(s) => s,
-        Err(_) => {
+    // Shortcuts of portable switchers leave out the osu! directory, which the switcher is in
+    let portable_dir = platform::portable_dir().map(|dir| dir.to_string_lossy().into_owned());
+    let osu_dir = match ctx.string_flag("osu").ok().or(portable_dir) {
+        Some(s) => s,
+        None => {
             return Err(ActionError {
                 message: "The --osu flag is required in order to start osu".to_owned(),
             });
@@ -59,8 +213,216 @@ fn switch(ctx: &Context) -> ActionResult {
     };
     let server = ctx
         .string_flag("server")
-        .unwrap_or("osu.ppy.sh".to_string());
+        .unwrap_or(servers::BANCHO.to_owned());
+
+    if let Ok(format) = ctx.uint_flag("shortcut-format")
+        && format < shortcuts::ARGS_FORMAT
+    {
+        println!(
+            "Warning: this shortcut was created by an older version of osu!switcher (argument \
+            format {format}, now {0}). Open osu!switcher again to update the shortcuts.",
+            shortcuts::ARGS_FORMAT
+        );
+    }
+    let on_repair = match ctx.string_flag("on-repair") {
+        Ok(policy) => Some(policy.parse::<RepairPolicy>().map_err(|err| ActionError {
+            message: err.to_string(),
+        })?),
+        Err(_) => None,
+    };
+
+    let mut wine = Wine::default();
+    if let Ok(binary) = ctx.string_flag("wine") {
+        wine.binary = binary;
+    }
+    if let Ok(prefix) = ctx.string_flag("wine-prefix") {
+        wine.prefix = PathBuf::from(prefix);
+    }
+    let launcher = match ctx.string_flag("launcher").as_deref() {
+        Ok("native") => Launcher::Native,
+        Ok("wine") => Launcher::Wine(wine),
+        Ok(launcher) => {
+            return Err(ActionError {
+                message: format!("unknown launcher {launcher:?}, expected native or wine"),
+            });
+        }
+        Err(_) => Launcher::detect(wine),
+    };
+    // Shortcuts made inside of Wine point to the Windows path of osu!
+    let osu_dir = launcher.resolve_path(&*osu_dir).to_string_lossy().into_owned();
+
+    // Shortcuts created before the argument format was introduced don't pass it at all
+    if ctx.uint_flag("shortcut-format").is_err()
+        && shortcuts::has_outdated_shortcut(Path::new(&*osu_dir), &*server)
+    {
+        println!(
+            "Warning: the shortcut for {server} was created by an older version of osu!switcher. \
+            Open osu!switcher again to update the shortcuts."
+        );
+    }
+
+    let options = SwitchOptions {
+        server,
+        account: ctx.string_flag("account").ok(),
+        dry_run: ctx.bool_flag("dry-run"),
+        on_repair,
+        kill_all: ctx.bool_flag("kill-all"),
+        launcher,
+        system_user: None,
+    };
+
+    switcher::switch_servers(&*osu_dir, &options).unwrap();
+    Ok(())
+}
+
+fn history(ctx: &Context) -> ActionResult {
+    let osu_dir = match ctx.string_flag("osu") {
+        Ok(s) => s,
+        Err(_) => {
+            return Err(ActionError {
+                message: "The --osu flag is required in order to read the history".to_owned(),
+            });
+        }
+    };
+    let limit = ctx.uint_flag("limit").unwrap_or(20);
+
+    history::print(Path::new(&*osu_dir), limit).unwrap();
+    Ok(())
+}
+
+fn status(ctx: &Context) -> ActionResult {
+    let osu_dir = match ctx.string_flag("osu") {
+        Ok(s) => s,
+        Err(_) => {
+            return Err(ActionError {
+                message: "The --osu flag is required in order to read the status".to_owned(),
+            });
+        }
+    };
+
+    switcher::status::print_status(Path::new(&*osu_dir)).unwrap();
+    Ok(())
+}
+
+fn accounts_list(ctx: &Context) -> ActionResult {
+    let osu_dir = require_osu_flag(ctx)?;
+
+    switcher::accounts::list(Path::new(&*osu_dir)).unwrap();
+    Ok(())
+}
+
+fn accounts_forget(ctx: &Context) -> ActionResult {
+    let osu_dir = require_osu_flag(ctx)?;
+    let [server] = &*ctx.args else {
+        return Err(ActionError {
+            message: "Exactly one server to forget the sessions of is required".to_owned(),
+        });
+    };
+    let account = ctx.string_flag("account").ok();
+
+    switcher::accounts::forget(
+        Path::new(&*osu_dir),
+        server,
+        account.as_deref(),
+        ctx.bool_flag("yes"),
+    )
+    .unwrap();
+    Ok(())
+}
+
+fn accounts_move(ctx: &Context) -> ActionResult {
+    let osu_dir = require_osu_flag(ctx)?;
+    let [old_server, new_server] = &*ctx.args else {
+        return Err(ActionError {
+            message: "Both the old and the new server are required".to_owned(),
+        });
+    };
+
+    switcher::accounts::move_server(
+        Path::new(&*osu_dir),
+        old_server,
+        new_server,
+        ctx.bool_flag("yes"),
+    )
+    .unwrap();
+    Ok(())
+}
+
+fn export(ctx: &Context) -> ActionResult {
+    let osu_dir = require_osu_flag(ctx)?;
+    let bundle_file = require_bundle_file(ctx)?;
+    let passphrase = ctx.string_flag("passphrase").ok();
+
+    bundle::export(Path::new(&*osu_dir), Path::new(bundle_file), passphrase.as_deref()).unwrap();
+    Ok(())
+}
+
+fn import(ctx: &Context) -> ActionResult {
+    let osu_dir = require_osu_flag(ctx)?;
+    let bundle_file = require_bundle_file(ctx)?;
+    let passphrase = ctx.string_flag("passphrase").ok();
 
-    switcher::switch_servers(&*osu_dir, &*server).unwrap();
+    bundle::import(
+        Path::new(&*osu_dir),
+        Path::new(bundle_file),
+        passphrase.as_deref(),
+        !ctx.bool_flag("no-shortcuts"),
+    )
+    .unwrap();
+    Ok(())
+}
+
+fn settings(ctx: &Context) -> ActionResult {
+    let osu_dir = require_osu_flag(ctx)?;
+
+    match &*ctx.args {
+        [] => switcher::settings::list(Path::new(&*osu_dir)).unwrap(),
+        [name, value] => switcher::settings::set(Path::new(&*osu_dir), name, value).unwrap(),
+        _ => {
+            return Err(ActionError {
+                message: "Either no arguments, or a setting name and its value are required"
+                    .to_owned(),
+            });
+        }
+    }
     Ok(())
 }
+
+fn relocate(ctx: &Context) -> ActionResult {
+    let (Ok(from), Ok(to)) = (ctx.string_flag("from"), ctx.string_flag("to")) else {
+        return Err(ActionError {
+            message: "Both the --from and --to flags are required to relocate".to_owned(),
+        });
+    };
+
+    relocate::relocate(Path::new(&*from), Path::new(&*to)).unwrap();
+    Ok(())
+}
+
+fn uninstall(ctx: &Context) -> ActionResult {
+    let osu_dir = require_osu_flag(ctx)?;
+
+    let removed = uninstall::uninstall(Path::new(&*osu_dir), ctx.bool_flag("purge")).unwrap();
+    if removed.is_empty() {
+        println!("Nothing to delete, the switcher is not installed");
+    }
+    for path in removed {
+        println!("Deleted {path:?}");
+    }
+    Ok(())
+}
+
+fn require_osu_flag(ctx: &Context) -> Result<String, ActionError> {
+    ctx.string_flag("osu").map_err(|_| ActionError {
+        message: "The --osu flag is required to find the stored sessions".to_owned(),
+    })
+}
+
+fn require_bundle_file(ctx: &Context) -> Result<&str, ActionError> {
+    match &*ctx.args {
+        [bundle_file] => Ok(bundle_file),
+        _ => Err(ActionError {
+            message: "Exactly one bundle file path is required".to_owned(),
+        }),
+    }
+}
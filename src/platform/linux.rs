@@ -0,0 +1,253 @@
+use crate::launcher::Wine;
+use crate::osu_util::check_osu_installation;
+use crate::platform::Shortcut;
+use crate::servers;
+use color_eyre::eyre::{bail, Context, ContextCompat};
+use color_eyre::Result;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::{env, fs};
+
+/// The file name of the installed switcher executable.
+pub const SWITCHER_EXE: &str = "osu!switcher";
+
+/// The file extension of shortcuts.
+pub const SHORTCUT_EXTENSION: &str = "desktop";
+
+/// Attempts to find an osu! stable installation in the default Wine prefix,
+/// at the locations the osu! installer suggests.
+pub fn find_osu_installation() -> Option<PathBuf> {
+    let drive_c = Wine::default().prefix.join("drive_c");
+    let username = whoami::username().ok()?;
+
+    [
+        drive_c.join(format!("users/{username}/AppData/Local/osu!")),
+        drive_c.join("osu!"),
+        drive_c.join("Program Files/osu!"),
+    ]
+    .into_iter()
+    .find(|osu_dir| check_osu_installation(osu_dir))
+}
+
+/// Returns the user's home directory.
+fn home_dir() -> Result<PathBuf> {
+    let home = env::var_os("HOME").context("HOME environment variable unset")?;
+    Ok(PathBuf::from(home))
+}
+
+/// Returns the user's XDG data directory, which is `~/.local/share` by default.
+fn data_dir() -> Result<PathBuf> {
+    match env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        Some(data_dir) => Ok(PathBuf::from(data_dir)),
+        None => Ok(home_dir()?.join(".local/share")),
+    }
+}
+
+/// Returns the permanent installation location of this switcher, in the XDG data directory.
+pub fn default_install_dir() -> Result<PathBuf> {
+    Ok(data_dir()?.join("osu!switcher"))
+}
+
+/// Returns the user's icon theme directory, which icons are looked up in by their name.
+pub fn icon_theme_dir() -> Result<PathBuf> {
+    Ok(data_dir()?.join("icons/hicolor"))
+}
+
+/// Returns the path to the user's Desktop from the XDG user directories.
+fn desktop_dir() -> Result<PathBuf> {
+    let home = home_dir()?;
+    let desktop_path = match env::var_os("XDG_DESKTOP_DIR").filter(|dir| !dir.is_empty()) {
+        Some(desktop_path) => PathBuf::from(desktop_path),
+        None => {
+            let config_dir = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+                Some(config_dir) => PathBuf::from(config_dir),
+                None => home.join(".config"),
+            };
+
+            fs::read_to_string(config_dir.join("user-dirs.dirs"))
+                .ok()
+                .and_then(|user_dirs| parse_user_dirs(&*user_dirs, &*home))
+                .unwrap_or_else(|| home.join("Desktop"))
+        }
+    };
+
+    if !fs::exists(&*desktop_path).unwrap_or(false) {
+        bail!("user desktop directory does not exist!");
+    }
+
+    Ok(desktop_path)
+}
+
+/// Reads the desktop directory from `user-dirs.dirs`, which is made of shell variable assignments
+/// such as `XDG_DESKTOP_DIR="$HOME/Desktop"`.
+fn parse_user_dirs(user_dirs: &str, home: &Path) -> Option<PathBuf> {
+    let value = user_dirs
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("XDG_DESKTOP_DIR="))?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+    // Paths are either relative to the home directory, or absolute
+    match value.strip_prefix("$HOME") {
+        Some(path) => Some(home.join(path.trim_start_matches('/'))),
+        None => value.starts_with('/').then(|| PathBuf::from(value)),
+    }
+}
+
+/// Returns the directories shortcuts are created in, which are the applications menu,
+/// and the user's Desktop if there is one.
+pub fn shortcut_dirs() -> Result<Vec<PathBuf>> {
+    let applications_dir = data_dir()?.join("applications");
+    fs::create_dir_all(&*applications_dir)
+        .with_context(|| format!("failed to create applications dir {applications_dir:?}"))?;
+
+    let mut dirs = vec![applications_dir];
+    dirs.extend(desktop_dir().ok());
+    Ok(dirs)
+}
+
+/// Creates a `.desktop` launcher file.
+pub fn create_shortcut(path: &Path, shortcut: &Shortcut) -> Result<()> {
+    fs::write(path, desktop_entry(shortcut)?)
+        .with_context(|| format!("failed to create shortcut {path:?}"))?;
+
+    // Desktop environments only start launchers on the desktop that are executable
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("failed to make shortcut executable {path:?}"))?;
+    Ok(())
+}
+
+/// Builds the contents of a desktop entry that starts the switcher.
+fn desktop_entry(shortcut: &Shortcut) -> Result<String> {
+    let target = shortcut
+        .target
+        .to_str()
+        .context("switcher path contains invalid characters")?;
+
+    let exec: Vec<String> = [target]
+        .iter()
+        .chain(shortcut.args)
+        .map(|arg| quote_exec_arg(arg))
+        .collect();
+
+    Ok(format!(
+        "[Desktop Entry]\n\
+        Type=Application\n\
+        Name={0}\n\
+        Comment=Switch osu! to a different server account\n\
+        Exec={1}\n\
+        Icon={2}\n\
+        Terminal=false\n\
+        Categories=Game;\n",
+        escape_value(shortcut.name),
+        escape_value(&*exec.join(" ")),
+        escape_value(shortcut.icon),
+    ))
+}
+
+/// Quotes an argument of the `Exec` key of a desktop entry if it contains any reserved characters.
+fn quote_exec_arg(arg: &str) -> String {
+    const RESERVED: &[char] = &[
+        ' ', '\t', '\n', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(',
+        ')', '`',
+    ];
+
+    // Field codes such as %f would be replaced by the desktop environment
+    let arg = arg.replace('%', "%%");
+    if !arg.is_empty() && !arg.contains(RESERVED) {
+        return arg;
+    }
+
+    let mut quoted = String::from('"');
+    for char in arg.chars() {
+        if matches!(char, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(char);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Escapes a string value of a desktop entry.
+fn escape_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+}
+
+/// Launches osu! with a specified server, which requires Wine to be registered to run `.exe`s.
+pub fn launch_osu(osu_exe: &Path, server: &str) -> Result<()> {
+    let mut command = Command::new(osu_exe);
+
+    let endpoint = servers::endpoint(server);
+    if !endpoint.is_empty() {
+        command.args(["-devserver", &*endpoint]);
+    }
+    if let Some(osu_dir) = osu_exe.parent() {
+        command.current_dir(osu_dir);
+    }
+
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("failed to start osu!")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_exec_args() {
+        assert_eq!(quote_exec_arg("switch"), "switch");
+        assert_eq!(quote_exec_arg("/games/osu!"), "/games/osu!");
+        assert_eq!(quote_exec_arg("/games/my osu!"), "\"/games/my osu!\"");
+        assert_eq!(quote_exec_arg("$HOME\\\"`"), "\"\\$HOME\\\\\\\"\\`\"");
+        assert_eq!(quote_exec_arg("100%"), "100%%");
+        assert_eq!(quote_exec_arg(""), "\"\"");
+    }
+
+    #[test]
+    fn writes_desktop_entry() {
+        let shortcut = Shortcut {
+            name: "osu! (akatsuki.gg)",
+            target: Path::new("/home/tester/.local/share/osu!switcher/osu!switcher"),
+            args: &["switch", "--osu", "/games/my osu!", "--server", "akatsuki.gg"],
+            icon: "osu-switcher-akatsuki.gg",
+        };
+
+        assert_eq!(
+            desktop_entry(&shortcut).unwrap(),
+            include_str!("../../tests/fixtures/shortcuts/akatsuki.gg.desktop")
+        );
+    }
+
+    #[test]
+    fn reads_user_dirs() {
+        let home = Path::new("/home/tester");
+
+        assert_eq!(
+            parse_user_dirs("# Comment\nXDG_DESKTOP_DIR=\"$HOME/Schreibtisch\"\n", home),
+            Some(home.join("Schreibtisch"))
+        );
+        assert_eq!(
+            parse_user_dirs("XDG_DESKTOP_DIR=\"/mnt/desktop\"", home),
+            Some(PathBuf::from("/mnt/desktop"))
+        );
+        assert_eq!(parse_user_dirs("XDG_MUSIC_DIR=\"$HOME/Music\"", home), None);
+    }
+
+    #[test]
+    fn escapes_exec_value() {
+        // Backslashes are escaped once for quoting, and once more for the string value
+        let exec = quote_exec_arg("C:\\osu! folder");
+        assert_eq!(escape_value(&*exec), "\"C:\\\\\\\\osu! folder\"");
+    }
+}
@@ -0,0 +1,51 @@
+use color_eyre::Result;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(windows)]
+mod windows;
+
+// Everything else that differs between systems, which is how running osu! processes are
+// found and stopped, lives in [crate::process]
+#[cfg(target_os = "linux")]
+pub use linux::*;
+#[cfg(windows)]
+pub use windows::*;
+
+/// The file name of the marker that this switcher was installed into an osu! directory in
+/// portable mode, which keeps all of its state next to the game.
+pub const PORTABLE_FILE_NAME: &str = "osu!switcher.portable";
+
+/// Returns the osu! directory that the running switcher was installed into in portable mode.
+pub fn portable_dir() -> Option<PathBuf> {
+    let this_exe = env::current_exe().ok()?;
+    let exe_dir = this_exe.parent()?;
+
+    fs::exists(exe_dir.join(PORTABLE_FILE_NAME))
+        .unwrap_or(false)
+        .then(|| exe_dir.to_owned())
+}
+
+/// Returns the installation location of this switcher for an osu! directory, which is the osu!
+/// directory itself if the switcher was installed into it in portable mode.
+pub fn install_dir(osu_dir: &Path) -> Result<PathBuf> {
+    if fs::exists(osu_dir.join(PORTABLE_FILE_NAME)).unwrap_or(false) {
+        return Ok(osu_dir.to_owned());
+    }
+
+    default_install_dir()
+}
+
+/// A shortcut that starts the switcher, like the ones generated onto the desktop.
+pub struct Shortcut<'a> {
+    /// The name that is shown for the shortcut.
+    pub name: &'a str,
+    /// The switcher executable the shortcut starts.
+    pub target: &'a Path,
+    /// The arguments the switcher is started with.
+    pub args: &'a [&'a str],
+    /// The icon shown for the shortcut, as a file path (or an icon theme name on Linux).
+    pub icon: &'a str,
+}
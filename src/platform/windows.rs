@@ -0,0 +1,130 @@
+use crate::osu_util::{check_osu_installation, flatten_osu_installation};
+use crate::platform::Shortcut;
+use crate::servers;
+use color_eyre::eyre::{bail, Context, ContextCompat};
+use color_eyre::Result;
+use mslnk::ShellLink;
+use std::borrow::Cow;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::{env, fs};
+
+/// The file name of the installed switcher executable.
+pub const SWITCHER_EXE: &str = "osu!switcher.exe";
+
+/// The file extension of shortcuts.
+pub const SHORTCUT_EXTENSION: &str = "lnk";
+
+/// Attempts to retrieve an osu! stable installation based on the associated osu! stable application
+/// to open `*.osz` files with from the Windows registry.
+pub fn find_osu_installation() -> Option<PathBuf> {
+    let open_cmd = windows_registry::CLASSES_ROOT
+        .open("osustable.File.osz\\Shell\\Open\\Command")
+        .and_then(|key| key.get_string(""))
+        .ok()?;
+
+    let osu_exe = Path::new(open_cmd.split('"').nth(1)?);
+    let osu_dir = flatten_osu_installation(osu_exe);
+
+    check_osu_installation(&*osu_dir).then(|| osu_dir.into_owned())
+}
+
+/// Returns the permanent installation location of this switcher.
+pub fn default_install_dir() -> Result<PathBuf> {
+    let localappdata = env::var_os("LOCALAPPDATA")
+        .context("LOCALAPPDATA environment variable unset")?;
+
+    Ok(Path::new(&*localappdata).join("osu!switcher"))
+}
+
+/// Returns the path to the user's Desktop.
+fn desktop_dir() -> Result<PathBuf> {
+    let home_path = env::var_os("USERPROFILE")
+        .context("USERPROFILE environment variable unset")?;
+    let desktop_path = Path::new(&*home_path).join("Desktop");
+
+    if !fs::exists(&*desktop_path).unwrap_or(false) {
+        bail!("user desktop directory does not exist!");
+    }
+
+    Ok(desktop_path)
+}
+
+/// Returns the directories shortcuts are created in, which is only the user's Desktop.
+pub fn shortcut_dirs() -> Result<Vec<PathBuf>> {
+    Ok(vec![desktop_dir()?])
+}
+
+/// Creates a `.lnk` shortcut file.
+pub fn create_shortcut(path: &Path, shortcut: &Shortcut) -> Result<()> {
+    let args: Vec<Cow<str>> = shortcut.args.iter().map(|arg| quote_arg(arg)).collect();
+
+    let mut link = ShellLink::new(shortcut.target)
+        .with_context(|| format!("failed to create shortcut {0:?}", shortcut.target))?;
+    link.set_arguments(Some(args.join(" ")));
+    // Lets Windows find the switcher again if the drive letter changed, such as for USB drives
+    if let Some(relative_path) = path.parent().and_then(|dir| relative_path(dir, shortcut.target)) {
+        link.set_relative_path(Some(relative_path));
+    }
+    link.set_icon_location(Some(shortcut.icon.to_owned()));
+    link.set_name(Some(shortcut.name.to_owned()));
+
+    link.create_lnk(path)
+        .with_context(|| format!("failed to create shortcut {path:?}"))?;
+    Ok(())
+}
+
+/// Returns the path to a file relative to a directory, if both are on the same drive.
+fn relative_path(from_dir: &Path, to: &Path) -> Option<String> {
+    let from_dir: Vec<Component> = from_dir.components().collect();
+    let to: Vec<Component> = to.components().collect();
+
+    if from_dir.first() != to.first() {
+        return None;
+    }
+
+    let common = from_dir.iter().zip(&*to).take_while(|(a, b)| a == b).count();
+    let mut relative_path = PathBuf::from(".");
+    relative_path.extend((common..from_dir.len()).map(|_| ".."));
+    relative_path.extend(&to[common..]);
+
+    relative_path.to_str().map(str::to_owned)
+}
+
+/// Quotes an argument for a Windows command line if it contains any spaces or quotes.
+fn quote_arg(arg: &str) -> Cow<'_, str> {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.into();
+    }
+
+    // Backslashes only need to be escaped when they come right before a quote
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for char in arg.chars() {
+        match char {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes + 1));
+                backslashes = 0;
+            }
+            _ => backslashes = 0,
+        }
+        quoted.push(char);
+    }
+    quoted.push_str(&"\\".repeat(backslashes));
+    quoted.push('"');
+
+    quoted.into()
+}
+
+/// Launches osu! with a specified server.
+pub fn launch_osu(osu_exe: &Path, server: &str) -> Result<()> {
+    Command::new("cmd")
+        .args(["/C", "start"])
+        .arg(osu_exe)
+        .args(["-devserver", &*servers::endpoint(server)])
+        .spawn()
+        .context("failed to start osu!")?;
+
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use crate::process::{ProcessController, ProcessInfo};
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// An in-memory process list for testing, where processes exit immediately upon being closed
+/// or killed, unless marked otherwise.
+#[derive(Default)]
+pub struct FakeController {
+    processes: RefCell<Vec<ProcessInfo>>,
+    /// Processes that ignore being asked to close.
+    ignores_close: Vec<u32>,
+    /// Processes that ignore being killed, and never exit.
+    unkillable: Vec<u32>,
+    /// Every PID that was asked to close, in order.
+    closed: RefCell<Vec<u32>>,
+    /// Every PID that was killed, in order.
+    killed: RefCell<Vec<u32>>,
+}
+
+impl FakeController {
+    /// Adds a running process with the specified executable name.
+    pub fn with_process(self, pid: u32, name: &str) -> Self {
+        self.processes.borrow_mut().push(ProcessInfo {
+            pid,
+            name: name.to_owned(),
+            exe: None,
+            args: Vec::new(),
+        });
+        self
+    }
+
+    /// Adds a running process started from the specified executable.
+    pub fn with_process_at(self, pid: u32, exe: &str) -> Self {
+        let exe = PathBuf::from(exe);
+        let name = exe.file_name().unwrap().to_string_lossy().into_owned();
+
+        self.processes.borrow_mut().push(ProcessInfo {
+            pid,
+            name,
+            exe: Some(exe),
+            args: Vec::new(),
+        });
+        self
+    }
+
+    /// Sets the arguments that a running process was started with.
+    pub fn started_with(self, pid: u32, args: &[&str]) -> Self {
+        for process in self.processes.borrow_mut().iter_mut() {
+            if process.pid == pid {
+                process.args = args.iter().map(|arg| (*arg).to_owned()).collect();
+            }
+        }
+        self
+    }
+
+    /// Makes a process ignore being asked to close.
+    pub fn ignores_close(mut self, pid: u32) -> Self {
+        self.ignores_close.push(pid);
+        self
+    }
+
+    /// Makes a process ignore being killed.
+    pub fn unkillable(mut self, pid: u32) -> Self {
+        self.unkillable.push(pid);
+        self
+    }
+
+    /// Returns every PID that was asked to close, in order.
+    pub fn closed(&self) -> Vec<u32> {
+        self.closed.borrow().clone()
+    }
+
+    /// Returns every PID that was killed, in order.
+    pub fn killed(&self) -> Vec<u32> {
+        self.killed.borrow().clone()
+    }
+}
+
+impl ProcessController for FakeController {
+    fn processes(&self) -> Result<Vec<ProcessInfo>> {
+        Ok(self.processes.borrow().clone())
+    }
+
+    fn is_running(&self, pid: u32) -> bool {
+        self.processes.borrow().iter().any(|process| process.pid == pid)
+    }
+
+    fn kill(&self, pid: u32) -> Result<()> {
+        if !self.is_running(pid) {
+            bail!("no process with PID {pid}");
+        }
+
+        self.killed.borrow_mut().push(pid);
+        if !self.unkillable.contains(&pid) {
+            self.processes.borrow_mut().retain(|process| process.pid != pid);
+        }
+        Ok(())
+    }
+
+    fn close(&self, pid: u32) -> Result<()> {
+        if !self.is_running(pid) {
+            bail!("no process with PID {pid}");
+        }
+
+        self.closed.borrow_mut().push(pid);
+        if !self.ignores_close.contains(&pid) {
+            self.processes.borrow_mut().retain(|process| process.pid != pid);
+        }
+        Ok(())
+    }
+
+    fn wait(&self, pid: u32, _timeout: Duration) -> bool {
+        // Nothing changes while waiting, so there's no need to actually wait
+        !self.is_running(pid)
+    }
+}
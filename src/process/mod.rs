@@ -0,0 +1,281 @@
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+pub mod fake;
+#[cfg(not(target_os = "linux"))]
+mod native;
+#[cfg(target_os = "linux")]
+mod procfs;
+
+#[cfg(not(target_os = "linux"))]
+pub use native::NativeController;
+#[cfg(target_os = "linux")]
+pub use procfs::ProcFsController;
+
+/// A snapshot of a single running process.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// The file name of the executable, such as `osu!.exe`.
+    pub name: String,
+    /// The full path to the executable, if it could be read.
+    pub exe: Option<PathBuf>,
+    /// The arguments the process was started with, excluding the executable itself.
+    pub args: Vec<String>,
+}
+
+impl ProcessInfo {
+    /// Whether this is an osu! stable process (including its updater).
+    pub fn is_osu(&self) -> bool {
+        self.name.eq_ignore_ascii_case("osu!.exe") || self.name.eq_ignore_ascii_case("osu!")
+    }
+
+    /// The value of osu!'s `-devserver` argument, which is empty (Bancho) if it wasn't passed.
+    pub fn devserver(&self) -> &str {
+        self.args
+            .iter()
+            .position(|arg| arg.eq_ignore_ascii_case("-devserver"))
+            .and_then(|idx| self.args.get(idx + 1))
+            .map_or("", |arg| &**arg)
+    }
+
+    /// Whether this process was started from an executable directly inside a directory.
+    /// Processes whose executable can't be read are assumed not to be, since they might belong
+    /// to another installation.
+    pub fn runs_from(&self, dir: &Path) -> bool {
+        self.exe
+            .as_deref()
+            .and_then(Path::parent)
+            .is_some_and(|exe_dir| is_same_path(exe_dir, dir))
+    }
+}
+
+/// Checks whether two paths point to the same location, resolving links where possible.
+pub fn is_same_path(a: &Path, b: &Path) -> bool {
+    let a = fs::canonicalize(a).unwrap_or_else(|_| a.to_owned());
+    let b = fs::canonicalize(b).unwrap_or_else(|_| b.to_owned());
+
+    // Windows paths are case-insensitive
+    if cfg!(windows) {
+        a.to_string_lossy().eq_ignore_ascii_case(&*b.to_string_lossy())
+    } else {
+        a == b
+    }
+}
+
+/// Finds, kills and waits for processes on the current system.
+pub trait ProcessController {
+    /// Lists every running process.
+    fn processes(&self) -> Result<Vec<ProcessInfo>>;
+
+    /// Checks whether a process with the specified PID is currently running.
+    fn is_running(&self, pid: u32) -> bool;
+
+    /// Forcefully kills a process, without waiting for it to exit.
+    fn kill(&self, pid: u32) -> Result<()>;
+
+    /// Asks a process to close by itself, without waiting for it to exit.
+    fn close(&self, pid: u32) -> Result<()>;
+
+    /// Waits for a process to exit. Returns whether it exited before the timeout.
+    fn wait(&self, pid: u32, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+
+        while self.is_running(pid) {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        true
+    }
+
+    /// Lists every running osu! process, or only the ones running from an osu! installation.
+    fn find_osu(&self, osu_dir: Option<&Path>) -> Result<Vec<ProcessInfo>> {
+        let mut processes: Vec<ProcessInfo> = self
+            .processes()?
+            .into_iter()
+            .filter(ProcessInfo::is_osu)
+            .filter(|process| osu_dir.is_none_or(|osu_dir| process.runs_from(osu_dir)))
+            .collect();
+
+        processes.sort_unstable_by_key(|process| process.pid);
+        Ok(processes)
+    }
+}
+
+/// How long to wait for a process to exit after killing it.
+const KILL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Stops every osu! process running from an osu! installation (or every osu! process at all
+/// if unspecified), and waits for them to exit. If a close timeout is specified, then osu! is
+/// first asked to close by itself, and only killed if it is still running after that timeout.
+/// Returns the amount of processes that were stopped.
+pub fn stop_osu(
+    controller: &dyn ProcessController,
+    osu_dir: Option<&Path>,
+    close_timeout: Option<Duration>,
+) -> Result<usize> {
+    let processes = controller.find_osu(osu_dir)?;
+
+    if osu_dir.is_some() {
+        let unknown = controller
+            .find_osu(None)?
+            .into_iter()
+            .filter(|process| process.exe.is_none());
+
+        for process in unknown {
+            println!(
+                "Leaving osu! (PID {0}) running, its installation is unknown (use --kill-all to \
+                stop it)",
+                process.pid
+            );
+        }
+    }
+
+    let mut remaining: Vec<u32> = processes.iter().map(|process| process.pid).collect();
+
+    if let Some(close_timeout) = close_timeout {
+        for pid in &remaining {
+            controller.close(*pid)?;
+        }
+
+        let deadline = Instant::now() + close_timeout;
+        remaining.retain(|pid| {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            !controller.wait(*pid, timeout)
+        });
+
+        if !remaining.is_empty() {
+            println!("osu! did not close within {0}s, killing it", close_timeout.as_secs());
+        }
+    }
+
+    for pid in &remaining {
+        controller.kill(*pid)?;
+    }
+
+    for pid in &remaining {
+        if !controller.wait(*pid, KILL_TIMEOUT) {
+            bail!(
+                "osu! (PID {pid}) did not exit within {0}s of killing it",
+                KILL_TIMEOUT.as_secs()
+            );
+        }
+    }
+
+    Ok(processes.len())
+}
+
+/// Returns the process controller for the current system.
+#[cfg(not(target_os = "linux"))]
+pub fn default_controller() -> Box<dyn ProcessController> {
+    Box::new(NativeController)
+}
+
+/// Returns the process controller for the current system.
+/// On Linux, osu! runs under Wine, which only [ProcFsController] can see through.
+#[cfg(target_os = "linux")]
+pub fn default_controller() -> Box<dyn ProcessController> {
+    Box::new(ProcFsController)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeController;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn kills_only_osu() {
+        let controller = FakeController::default()
+            .with_process(10, "explorer.exe")
+            .with_process(20, "osu!.exe")
+            .with_process(30, "osu!");
+
+        assert_eq!(stop_osu(&controller, None, None).unwrap(), 2);
+        assert_eq!(controller.killed(), vec![20, 30]);
+        assert!(controller.closed().is_empty());
+        assert!(controller.is_running(10));
+    }
+
+    #[test]
+    fn nothing_to_stop() {
+        let controller = FakeController::default().with_process(10, "explorer.exe");
+
+        assert_eq!(stop_osu(&controller, None, Some(TIMEOUT)).unwrap(), 0);
+        assert!(controller.killed().is_empty());
+        assert!(controller.closed().is_empty());
+    }
+
+    #[test]
+    fn closes_gracefully_before_killing() {
+        let controller = FakeController::default()
+            .with_process(20, "osu!.exe")
+            .with_process(30, "osu!.exe")
+            .ignores_close(30);
+
+        assert_eq!(stop_osu(&controller, None, Some(TIMEOUT)).unwrap(), 2);
+        assert_eq!(controller.closed(), vec![20, 30]);
+        assert_eq!(controller.killed(), vec![30]);
+        assert!(controller.find_osu(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn fails_if_osu_does_not_exit() {
+        let controller = FakeController::default()
+            .with_process(20, "osu!.exe")
+            .ignores_close(20)
+            .unkillable(20);
+
+        assert!(stop_osu(&controller, None, Some(TIMEOUT)).is_err());
+        assert!(controller.is_running(20));
+    }
+
+    #[test]
+    fn kills_only_target_installation() {
+        let controller = FakeController::default()
+            .with_process_at(20, "/games/osu/osu!.exe")
+            .with_process_at(30, "/games/osu-tourney/osu!.exe")
+            .with_process(40, "osu!.exe");
+
+        let osu_dir = Path::new("/games/osu");
+        assert_eq!(stop_osu(&controller, Some(osu_dir), None).unwrap(), 1);
+
+        // The executable of 40 is unknown, so it might belong to another installation
+        assert_eq!(controller.killed(), vec![20]);
+        assert!(controller.is_running(30));
+        assert!(controller.is_running(40));
+    }
+
+    #[test]
+    fn reads_devserver() {
+        let mut process = ProcessInfo {
+            pid: 20,
+            name: "osu!.exe".to_owned(),
+            exe: None,
+            args: Vec::new(),
+        };
+        assert_eq!(process.devserver(), "");
+
+        process.args = vec!["-devserver".to_owned(), "akatsuki.gg".to_owned()];
+        assert_eq!(process.devserver(), "akatsuki.gg");
+    }
+
+    #[test]
+    fn kills_all_installations() {
+        let controller = FakeController::default()
+            .with_process_at(20, "/games/osu/osu!.exe")
+            .with_process_at(30, "/games/osu-tourney/osu!.exe")
+            .with_process(40, "osu!.exe");
+
+        assert_eq!(stop_osu(&controller, None, None).unwrap(), 3);
+        assert_eq!(controller.killed(), vec![20, 30, 40]);
+    }
+}
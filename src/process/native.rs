@@ -0,0 +1,101 @@
+use crate::process::{ProcessController, ProcessInfo};
+use color_eyre::eyre::bail;
+#[cfg(windows)]
+use color_eyre::eyre::Context;
+use color_eyre::Result;
+#[cfg(windows)]
+use std::process::{Command, Stdio};
+#[cfg(not(windows))]
+use sysinfo::Signal;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, System, UpdateKind};
+
+/// Controls processes through the native APIs of the OS.
+pub struct NativeController;
+
+impl NativeController {
+    fn refresh(processes: ProcessesToUpdate) -> System {
+        let mut system = System::new();
+        system.refresh_processes_specifics(
+            processes,
+            true,
+            ProcessRefreshKind::nothing()
+                .with_exe(UpdateKind::OnlyIfNotSet)
+                .with_cmd(UpdateKind::OnlyIfNotSet),
+        );
+        system
+    }
+}
+
+impl ProcessController for NativeController {
+    fn processes(&self) -> Result<Vec<ProcessInfo>> {
+        let system = Self::refresh(ProcessesToUpdate::All);
+
+        let processes = system
+            .processes()
+            .values()
+            .filter(|process| process.status() != ProcessStatus::Zombie)
+            .map(|process| ProcessInfo {
+                pid: process.pid().as_u32(),
+                name: process.name().to_string_lossy().into_owned(),
+                exe: process.exe().map(|exe| exe.to_owned()),
+                args: process
+                    .cmd()
+                    .iter()
+                    .skip(1)
+                    .map(|arg| arg.to_string_lossy().into_owned())
+                    .collect(),
+            })
+            .collect();
+
+        Ok(processes)
+    }
+
+    fn is_running(&self, pid: u32) -> bool {
+        let pid = Pid::from_u32(pid);
+        let system = Self::refresh(ProcessesToUpdate::Some(&[pid]));
+
+        system
+            .process(pid)
+            .is_some_and(|process| process.status() != ProcessStatus::Zombie)
+    }
+
+    fn kill(&self, pid: u32) -> Result<()> {
+        let pid = Pid::from_u32(pid);
+        let system = Self::refresh(ProcessesToUpdate::Some(&[pid]));
+
+        match system.process(pid) {
+            // It might have exited in the meantime
+            None => Ok(()),
+            Some(process) if process.kill() => Ok(()),
+            Some(_) => bail!("failed to kill process {pid}"),
+        }
+    }
+
+    #[cfg(windows)]
+    fn close(&self, pid: u32) -> Result<()> {
+        // Without /F, this asks the process to close its windows like clicking the X button would
+        let status = Command::new("taskkill")
+            .args(["/PID", &*pid.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .context("failed to run taskkill")?;
+
+        // It might have exited in the meantime
+        if !status.success() && self.is_running(pid) {
+            bail!("failed to close process {pid}");
+        }
+        Ok(())
+    }
+
+    #[cfg(not(windows))]
+    fn close(&self, pid: u32) -> Result<()> {
+        let pid = Pid::from_u32(pid);
+        let system = Self::refresh(ProcessesToUpdate::Some(&[pid]));
+
+        match system.process(pid).map(|process| process.kill_with(Signal::Term)) {
+            None | Some(Some(true)) => Ok(()),
+            Some(_) => bail!("failed to close process {pid}"),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::launcher;
+use crate::process::{ProcessController, ProcessInfo};
+use color_eyre::eyre::{bail, Context};
+use color_eyre::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Controls processes by reading `/proc` directly, which also sees through Wine.
+///
+/// Processes running under Wine show up as the Wine loader in `/proc/<pid>/exe`,
+/// so the executable is taken from the command line instead, where Wine puts the Windows path.
+/// That is then translated back into a Unix path through the Wine prefix of the process.
+pub struct ProcFsController;
+
+impl ProcFsController {
+    fn read_process(pid: u32) -> Option<ProcessInfo> {
+        let dir = Path::new("/proc").join(pid.to_string());
+        let cmdline = fs::read(dir.join("cmdline")).ok()?;
+        let mut cmdline = cmdline
+            .split(|byte| *byte == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).into_owned());
+
+        let argv0 = cmdline.next();
+        let args = cmdline.collect();
+
+        let exe = match &argv0 {
+            Some(argv0) if argv0.to_ascii_lowercase().ends_with(".exe") => {
+                Some(launcher::to_unix_path(&*Self::wine_prefix(&*dir), argv0))
+            }
+            _ => fs::read_link(dir.join("exe")).ok(),
+        };
+
+        // Windows paths can't be split by the Linux path separators
+        let name = match &exe {
+            Some(exe) => exe.to_string_lossy().rsplit(['/', '\\']).next()?.to_owned(),
+            None => fs::read_to_string(dir.join("comm")).ok()?.trim_end().to_owned(),
+        };
+
+        Some(ProcessInfo { pid, name, exe, args })
+    }
+
+    /// Reads which Wine prefix a process was started in from its environment.
+    fn wine_prefix(dir: &Path) -> PathBuf {
+        let environ = fs::read(dir.join("environ")).unwrap_or_default();
+        let var = |name: &str| {
+            environ
+                .split(|byte| *byte == 0)
+                .find_map(|var| var.strip_prefix(format!("{name}=").as_bytes()))
+                .filter(|value| !value.is_empty())
+                .map(|value| String::from_utf8_lossy(value).into_owned())
+        };
+
+        launcher::default_prefix(var("WINEPREFIX").as_deref(), var("HOME").as_deref())
+    }
+
+    /// Sends a signal to a process. Processes that have already exited are ignored.
+    fn signal(pid: u32, signal: libc::c_int) -> Result<()> {
+        let Ok(raw_pid) = libc::pid_t::try_from(pid) else { bail!("invalid PID {pid}") };
+
+        // SAFETY: kill has no memory safety requirements
+        if unsafe { libc::kill(raw_pid, signal) } != 0 {
+            let err = std::io::Error::last_os_error();
+
+            // It might have exited in the meantime
+            if err.raw_os_error() != Some(libc::ESRCH) {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a process has exited, but has not been reaped by its parent yet.
+    fn is_zombie(pid: u32) -> bool {
+        let Ok(stat) = fs::read_to_string(format!("/proc/{pid}/stat")) else { return false };
+
+        // The state comes right after the executable name, which is wrapped in parentheses
+        stat.rsplit_once(')')
+            .is_some_and(|(_, rest)| rest.trim_start().starts_with('Z'))
+    }
+}
+
+impl ProcessController for ProcFsController {
+    fn processes(&self) -> Result<Vec<ProcessInfo>> {
+        let entries = fs::read_dir("/proc").context("failed to list /proc")?;
+
+        let processes = entries
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+            .filter(|pid| !Self::is_zombie(*pid))
+            .filter_map(Self::read_process)
+            .collect();
+
+        Ok(processes)
+    }
+
+    fn is_running(&self, pid: u32) -> bool {
+        fs::exists(format!("/proc/{pid}")).unwrap_or(false) && !Self::is_zombie(pid)
+    }
+
+    fn kill(&self, pid: u32) -> Result<()> {
+        Self::signal(pid, libc::SIGKILL).with_context(|| format!("failed to kill process {pid}"))
+    }
+
+    fn close(&self, pid: u32) -> Result<()> {
+        Self::signal(pid, libc::SIGTERM).with_context(|| format!("failed to close process {pid}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_current_process() {
+        let pid = std::process::id();
+        let processes = ProcFsController.processes().unwrap();
+        let current = processes.iter().find(|process| process.pid == pid).unwrap();
+
+        assert!(ProcFsController.is_running(pid));
+        assert_eq!(current.exe, std::env::current_exe().ok());
+    }
+}
@@ -0,0 +1,101 @@
+use crate::history;
+use crate::osu_util::{check_osu_installation, flatten_osu_installation};
+use crate::platform::{PORTABLE_FILE_NAME, SWITCHER_EXE};
+use crate::secrets::KEY_FILE_NAME;
+use crate::shortcuts;
+use crate::shortcuts::manifest::ShortcutManifest;
+use crate::shortcuts::{README_FILE_NAME, VERSION_FILE_NAME};
+use crate::store::SwitcherStore;
+use crate::switcher::lock::SwitchLock;
+use color_eyre::eyre::{bail, Context, ContextCompat};
+use color_eyre::Result;
+use std::fs;
+use std::path::Path;
+
+/// Moves everything the switcher keeps for an osu! installation that was moved to a new location:
+/// the switcher config and history (unless they were moved along with osu!), and the shortcuts
+/// along with their icons.
+pub fn relocate(from: &Path, to: &Path) -> Result<()> {
+    let from = flatten_osu_installation(from);
+    let to = flatten_osu_installation(to);
+    if !check_osu_installation(&*to) {
+        bail!("{to:?} is not an osu! installation");
+    }
+    if fs::exists(&*from).unwrap_or(false)
+        && fs::canonicalize(&*from).ok() == fs::canonicalize(&*to).ok()
+    {
+        bail!("{from:?} and {to:?} are the same osu! installation");
+    }
+
+    move_switcher_files(&*from, &*to)?;
+
+    let servers = shortcuts::relocate(&*from, &*to)?;
+    if servers.is_empty() {
+        println!("No shortcuts were created for {from:?}");
+    } else {
+        println!("Relocated shortcuts for {0}", servers.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Moves the switcher config (including backups) and the switch history to the new osu!
+/// directory, if the old one still exists and they weren't moved along with osu! already.
+/// Portable installations are moved over entirely.
+fn move_switcher_files(from: &Path, to: &Path) -> Result<()> {
+    if !fs::exists(from).unwrap_or(false) {
+        return Ok(());
+    }
+
+    let from_lock = SwitchLock::acquire(from)?;
+    let _to_lock = SwitchLock::acquire(to)?;
+
+    let mut files = SwitcherStore::files(from)?;
+    files.push(from.join(history::FILE_NAME));
+
+    if fs::exists(from.join(PORTABLE_FILE_NAME)).unwrap_or(false) {
+        files.extend(
+            [
+                SWITCHER_EXE,
+                README_FILE_NAME,
+                VERSION_FILE_NAME,
+                ShortcutManifest::FILE_NAME,
+                KEY_FILE_NAME,
+                PORTABLE_FILE_NAME,
+            ]
+            .map(|file_name| from.join(file_name)),
+        );
+    }
+
+    for file in files {
+        if !fs::exists(&*file).unwrap_or(false) {
+            continue;
+        }
+
+        let file_name = file.file_name().context("switcher file has no name")?;
+        let target = to.join(file_name);
+
+        if fs::exists(&*target).unwrap_or(false) {
+            println!("Keeping {target:?}, leaving {file:?} behind");
+            continue;
+        }
+
+        move_file(&*file, &*target)?;
+        println!("Moved {file:?} to {target:?}");
+    }
+
+    // Acquiring the lock created it, and nothing switches the old directory anymore
+    from_lock.remove()
+}
+
+/// Moves a file, which might be onto a different drive.
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    // Files can't be renamed across drives
+    fs::copy(from, to).with_context(|| format!("failed to copy {from:?} to {to:?}"))?;
+    fs::remove_file(from).with_context(|| format!("failed to delete {from:?}"))?;
+    Ok(())
+}
@@ -0,0 +1,266 @@
+use argon2::Argon2;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use color_eyre::eyre::{bail, eyre, Context, ContextCompat};
+use color_eyre::Result;
+use ini::Ini;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Provides the key that secrets (such as session keys) are encrypted with at rest.
+pub trait SecretProvider {
+    /// Retrieves the existing key, or `None` if one has not been created yet.
+    fn get_key(&self) -> Result<Option<Key>>;
+
+    /// Creates and persists a new key, replacing any existing one.
+    fn create_key(&self) -> Result<Key>;
+
+    /// Retrieves the existing key, creating one if it does not exist yet.
+    fn get_or_create_key(&self) -> Result<Key> {
+        match self.get_key()? {
+            Some(key) => Ok(key),
+            None => self.create_key(),
+        }
+    }
+}
+
+/// A key derived from a passphrase, with the salt and (unless supplied by the user) the passphrase
+/// stored in a key file outside the osu! directory. This works on any OS.
+///
+/// If the `OSU_SWITCHER_PASSPHRASE` environment variable is set when the key file is created,
+/// then that passphrase is used instead and never written to disk.
+pub struct KeyFileProvider {
+    path: PathBuf,
+}
+
+impl KeyFileProvider {
+    /// The environment variable that holds a user-chosen passphrase.
+    const PASSPHRASE_VAR: &'static str = "OSU_SWITCHER_PASSPHRASE";
+
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl SecretProvider for KeyFileProvider {
+    fn get_key(&self) -> Result<Option<Key>> {
+        if !fs::exists(&*self.path).unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let key_file = Ini::load_from_file(&*self.path)
+            .with_context(|| format!("failed to read key file {0:?}", self.path))?;
+        let section = key_file.general_section();
+
+        let salt = section.get("Salt").context("key file is missing its salt")?;
+        let salt = BASE64_STANDARD.decode(salt).context("corrupted key file salt")?;
+
+        let passphrase = match section.get("Passphrase") {
+            Some(passphrase) => passphrase.to_owned(),
+            None => env::var(Self::PASSPHRASE_VAR).with_context(|| {
+                format!("the {0} environment variable must be set", Self::PASSPHRASE_VAR)
+            })?,
+        };
+
+        derive_key(&*passphrase, &*salt).map(Some)
+    }
+
+    fn create_key(&self) -> Result<Key> {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+
+        let mut key_file = Ini::new();
+        key_file
+            .with_general_section()
+            .set("Salt", BASE64_STANDARD.encode(salt));
+
+        let passphrase = match env::var(Self::PASSPHRASE_VAR) {
+            Ok(passphrase) => passphrase,
+            Err(_) => {
+                let mut passphrase = [0u8; 32];
+                OsRng.fill_bytes(&mut passphrase);
+
+                let passphrase = BASE64_STANDARD.encode(passphrase);
+                key_file
+                    .with_general_section()
+                    .set("Passphrase", &*passphrase);
+                passphrase
+            }
+        };
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create key file directory {parent:?}"))?;
+        }
+        key_file
+            .write_to_file(&*self.path)
+            .with_context(|| format!("failed to write key file {0:?}", self.path))?;
+
+        derive_key(&*passphrase, &salt)
+    }
+}
+
+/// The prefix of values that have been encrypted, to tell them apart from plain text ones.
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+/// Whether a stored value has been encrypted by [encrypt].
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Masks a secret so that it can be printed safely.
+pub fn mask(secret: &str) -> &'static str {
+    if secret.is_empty() {
+        "<none>"
+    } else {
+        "<session key>"
+    }
+}
+
+/// Encrypts a value to be stored in a config file.
+pub fn encrypt(key: &Key, plaintext: &str) -> Result<String> {
+    let data = encrypt_bytes(key, plaintext.as_bytes())?;
+    Ok(format!("{ENCRYPTED_PREFIX}{0}", BASE64_STANDARD.encode(data)))
+}
+
+/// Decrypts a value that was encrypted with [encrypt].
+pub fn decrypt(key: &Key, value: &str) -> Result<String> {
+    let data = value
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .context("value is not encrypted")?;
+    let data = BASE64_STANDARD
+        .decode(data)
+        .context("corrupted encrypted value")?;
+
+    String::from_utf8(decrypt_bytes(key, &*data)?).context("corrupted encrypted value")
+}
+
+/// Encrypts arbitrary data, prefixing the output with the random nonce used.
+pub fn encrypt_bytes(key: &Key, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(key)
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| eyre!("failed to encrypt secret"))?;
+
+    Ok([nonce.as_slice(), &*ciphertext].concat())
+}
+
+/// Decrypts data that was encrypted with [encrypt_bytes].
+pub fn decrypt_bytes(key: &Key, data: &[u8]) -> Result<Vec<u8>> {
+    const NONCE_LEN: usize = 12;
+
+    if data.len() < NONCE_LEN {
+        bail!("corrupted encrypted data");
+    }
+
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(key)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| eyre!("failed to decrypt secret, was the key or passphrase changed?"))
+}
+
+/// Derives an encryption key from a passphrase with Argon2id.
+pub fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| eyre!("failed to derive key from passphrase: {err}"))?;
+
+    Ok(key)
+}
+
+/// The file name of the key file inside the installation location of this switcher.
+pub const KEY_FILE_NAME: &str = "osu!switcher.key";
+
+/// Returns the secret provider used to encrypt the switcher config of an osu! directory.
+pub fn default_provider(osu_dir: &Path) -> Result<Box<dyn SecretProvider>> {
+    let path = crate::platform::install_dir(osu_dir)?.join(KEY_FILE_NAME);
+    Ok(Box::new(KeyFileProvider::new(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{AuthDetails, SwitcherStore};
+    use crate::test_util::TempOsuDir;
+
+    const SALT: &[u8] = b"osu!switcher-test";
+
+    #[test]
+    fn round_trips_values() {
+        let key = derive_key("passphrase", SALT).unwrap();
+        let encrypted = encrypt(&key, "alicekey").unwrap();
+
+        assert!(is_encrypted(&*encrypted));
+        assert!(!encrypted.contains("alicekey"));
+        assert_eq!(decrypt(&key, &*encrypted).unwrap(), "alicekey");
+    }
+
+    #[test]
+    fn rejects_wrong_key() {
+        let key = derive_key("passphrase", SALT).unwrap();
+        let wrong_key = derive_key("another passphrase", SALT).unwrap();
+        let encrypted = encrypt(&key, "alicekey").unwrap();
+
+        assert!(decrypt(&wrong_key, &*encrypted).is_err());
+        assert!(decrypt(&key, "alicekey").is_err());
+    }
+
+    #[test]
+    fn encrypts_plaintext_passwords() {
+        let osu_dir = TempOsuDir::new("plaintext-passwords");
+        let config = osu_dir.join(SwitcherStore::FILE_NAME);
+        fs::write(&*config, include_str!("../tests/fixtures/store/v2_current.ini")).unwrap();
+
+        let secrets = Box::new(KeyFileProvider::new(osu_dir.join(KEY_FILE_NAME)));
+        let store = SwitcherStore::load_with(&*osu_dir, secrets).unwrap();
+        assert!(store.has_plaintext());
+        store.save().unwrap();
+        store.verify().unwrap();
+
+        let written = Ini::load_from_file(&*config).unwrap();
+        let password = written.get_from(Some("alice@akatsuki.gg"), "Password").unwrap();
+        assert!(is_encrypted(password));
+
+        let secrets = Box::new(KeyFileProvider::new(osu_dir.join(KEY_FILE_NAME)));
+        let store = SwitcherStore::load_with(&*osu_dir, secrets).unwrap();
+        assert!(!store.has_plaintext());
+        assert_eq!(store.get("akatsuki.gg", None).unwrap().password, "alicekey");
+    }
+
+    #[test]
+    fn keeps_sessions_without_key() {
+        let osu_dir = TempOsuDir::new("missing-key");
+        let key_file = osu_dir.join(KEY_FILE_NAME);
+
+        let secrets = Box::new(KeyFileProvider::new(key_file.clone()));
+        let mut store = SwitcherStore::load_with(&*osu_dir, secrets).unwrap();
+        store.put(&AuthDetails {
+            username: "alice".to_owned(),
+            password: "alicekey".to_owned(),
+            server: "akatsuki.gg".to_owned(),
+        });
+        store.save().unwrap();
+        let key = fs::read(&*key_file).unwrap();
+        fs::remove_file(&*key_file).unwrap();
+
+        let secrets = Box::new(KeyFileProvider::new(key_file.clone()));
+        let store = SwitcherStore::load_with(&*osu_dir, secrets).unwrap();
+        let auth = store.get("akatsuki.gg", None).unwrap();
+        assert_eq!(auth.username, "alice");
+        assert_eq!(auth.password, "");
+        assert!(store.changes().is_empty());
+
+        // Saving writes the undecryptable session back, so restoring the key recovers it
+        store.save().unwrap();
+        store.verify().unwrap();
+        fs::write(&*key_file, key).unwrap();
+
+        let secrets = Box::new(KeyFileProvider::new(key_file));
+        let store = SwitcherStore::load_with(&*osu_dir, secrets).unwrap();
+        assert_eq!(store.get("akatsuki.gg", None).unwrap().password, "alicekey");
+    }
+}
@@ -0,0 +1,61 @@
+/// The domain of the official osu! server (Bancho).
+pub const BANCHO: &str = "osu.ppy.sh";
+
+/// Servers that are known under multiple domains, as `(alias, canonical)` pairs.
+/// Servers that have moved to a new domain belong here, and stored sessions get moved along.
+pub const ALIASES: &[(&str, &str)] = &[
+    ("akatsuki.pw", "akatsuki.gg"), // Moved to new domain
+    ("ppy.sh", BANCHO),
+];
+
+/// Returns the canonical domain of a server, which is what sessions are stored under,
+/// shortcuts are named after, and osu! is launched with.
+pub fn canonicalize(server: &str) -> String {
+    let server = server.trim().trim_end_matches('/').to_lowercase();
+
+    match ALIASES.iter().find(|(alias, _)| *alias == server) {
+        Some((_, canonical)) => (*canonical).to_owned(),
+        None => server,
+    }
+}
+
+/// Returns the value of osu!'s `CredentialEndpoint` and `-devserver` argument for a server.
+pub fn endpoint(server: &str) -> String {
+    match &*canonicalize(server) {
+        BANCHO => String::new(), // Empty defaults to Bancho
+        server => server.to_owned(),
+    }
+}
+
+/// Returns the canonical domain of the server that osu!'s `CredentialEndpoint` points to.
+pub fn from_endpoint(endpoint: &str) -> String {
+    match endpoint {
+        "" => BANCHO.to_owned(),
+        endpoint => canonicalize(endpoint),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonicalizes_servers() {
+        assert_eq!(canonicalize(" Akatsuki.GG/ "), "akatsuki.gg");
+        assert_eq!(canonicalize("akatsuki.pw"), "akatsuki.gg");
+        assert_eq!(canonicalize("ppy.sh"), BANCHO);
+        assert_eq!(canonicalize("ripple.moe"), "ripple.moe");
+    }
+
+    #[test]
+    fn maps_endpoints() {
+        assert_eq!(endpoint(BANCHO), "");
+        assert_eq!(endpoint("ppy.sh"), "");
+        assert_eq!(endpoint("akatsuki.pw"), "akatsuki.gg");
+        assert_eq!(endpoint("ripple.moe"), "ripple.moe");
+
+        assert_eq!(from_endpoint(""), BANCHO);
+        assert_eq!(from_endpoint("akatsuki.pw"), "akatsuki.gg");
+        assert_eq!(from_endpoint("ripple.moe"), "ripple.moe");
+    }
+}
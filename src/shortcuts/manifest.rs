@@ -0,0 +1,269 @@
+use crate::process;
+use color_eyre::eyre::{bail, Context, ContextCompat};
+use color_eyre::Result;
+use ini::{Ini, Properties, SectionEntry};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A shortcut generated by this switcher.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ShortcutRecord {
+    /// Where the shortcut file was created.
+    pub path: PathBuf,
+    /// The server the shortcut switches to.
+    pub server: String,
+    /// The osu! directory the shortcut switches, as passed to `--osu`.
+    pub osu_dir: PathBuf,
+    /// The icon of the shortcut, as a file path (or an icon theme name on Linux).
+    pub icon: String,
+    /// The switcher executable that the shortcut starts.
+    pub target: PathBuf,
+    /// The format of the arguments the shortcut starts the switcher with.
+    pub args_format: usize,
+    /// The Wine prefix the shortcut starts osu! in, as passed to `--wine-prefix`.
+    pub wine_prefix: Option<PathBuf>,
+    /// The version of the switcher that created the shortcut.
+    pub version: String,
+}
+
+/// The manifest (`osu!switcher.shortcuts.ini`) in the installation location of this switcher,
+/// which records every shortcut it generated, for any osu! directory.
+///
+/// Each shortcut lives in its own `[Shortcut]` section, while the layout version is stored in the
+/// general section.
+pub struct ShortcutManifest {
+    path: PathBuf,
+    pub shortcuts: Vec<ShortcutRecord>,
+}
+
+impl ShortcutManifest {
+    /// The file name of the manifest inside the installation location of this switcher.
+    pub const FILE_NAME: &'static str = "osu!switcher.shortcuts.ini";
+
+    /// The version of the manifest layout written by this switcher.
+    const VERSION: u32 = 1;
+
+    /// The name of the sections that shortcuts are recorded in.
+    const SECTION: &'static str = "Shortcut";
+
+    /// Loads the manifest from the installation location of this switcher.
+    /// Shortcuts created before the manifest existed are not recorded in it.
+    pub fn load(install_dir: &Path) -> Result<Self> {
+        let path = install_dir.join(Self::FILE_NAME);
+
+        let shortcuts = if fs::exists(&*path).unwrap_or(false) {
+            let ini = Ini::load_from_file(&*path)
+                .with_context(|| format!("failed loading shortcut manifest {path:?}"))?;
+            Self::from_ini(&ini)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self { path, shortcuts })
+    }
+
+    fn from_ini(ini: &Ini) -> Result<Vec<ShortcutRecord>> {
+        let version = match ini.general_section().get("Version") {
+            Some(version) => version.parse::<u32>().context("invalid shortcut manifest version")?,
+            None => Self::VERSION,
+        };
+        if version > Self::VERSION {
+            bail!("shortcut manifest was written by a newer version of the switcher");
+        }
+
+        ini.section_all(Some(Self::SECTION))
+            .map(|section| {
+                let get = |key| {
+                    section
+                        .get(key)
+                        .with_context(|| format!("shortcut in manifest is missing {key}"))
+                };
+
+                // Shortcuts recorded before these were tracked are treated as outdated
+                let target = section.get("Target").unwrap_or_default();
+                let args_format = match section.get("ArgsFormat") {
+                    Some(format) => format.parse().context("invalid shortcut argument format")?,
+                    None => 1,
+                };
+
+                Ok(ShortcutRecord {
+                    path: PathBuf::from(get("Path")?),
+                    server: get("Server")?.to_owned(),
+                    osu_dir: PathBuf::from(get("OsuDir")?),
+                    icon: get("Icon")?.to_owned(),
+                    target: PathBuf::from(target),
+                    args_format,
+                    wine_prefix: section.get("WinePrefix").map(PathBuf::from),
+                    version: get("SwitcherVersion")?.to_owned(),
+                })
+            })
+            .collect()
+    }
+
+    fn to_ini(&self) -> Result<Ini> {
+        let mut ini = Ini::new();
+        ini.with_general_section()
+            .set("Version", Self::VERSION.to_string());
+
+        for shortcut in &self.shortcuts {
+            let path_str = |path: &Path| {
+                path.to_str()
+                    .map(str::to_owned)
+                    .with_context(|| format!("path contains invalid characters {path:?}"))
+            };
+
+            let mut section = Properties::new();
+            section.insert("Path", path_str(&*shortcut.path)?);
+            section.insert("Server", &*shortcut.server);
+            section.insert("OsuDir", path_str(&*shortcut.osu_dir)?);
+            section.insert("Icon", &*shortcut.icon);
+            section.insert("Target", path_str(&*shortcut.target)?);
+            section.insert("ArgsFormat", shortcut.args_format.to_string());
+            if let Some(wine_prefix) = shortcut.wine_prefix.as_deref() {
+                section.insert("WinePrefix", path_str(wine_prefix)?);
+            }
+            section.insert("SwitcherVersion", &*shortcut.version);
+
+            match ini.entry(Some(Self::SECTION.to_owned())) {
+                SectionEntry::Vacant(entry) => {
+                    entry.insert(section);
+                }
+                SectionEntry::Occupied(mut entry) => entry.append(section),
+            }
+        }
+
+        Ok(ini)
+    }
+
+    /// Writes the manifest to disk, or deletes it if no shortcuts are recorded anymore.
+    pub fn save(&self) -> Result<()> {
+        if self.shortcuts.is_empty() {
+            if fs::exists(&*self.path).unwrap_or(false) {
+                fs::remove_file(&*self.path).with_context(|| {
+                    format!("failed to delete shortcut manifest {0:?}", self.path)
+                })?;
+            }
+            return Ok(());
+        }
+
+        self.to_ini()?
+            .write_to_file(&*self.path)
+            .with_context(|| format!("failed to write shortcut manifest {0:?}", self.path))
+    }
+
+    /// Returns the recorded shortcut at a path.
+    pub fn get(&self, path: &Path) -> Option<&ShortcutRecord> {
+        self.shortcuts.iter().find(|shortcut| shortcut.path == path)
+    }
+
+    /// Records a shortcut, replacing whatever was previously recorded at the same path.
+    pub fn record(&mut self, record: ShortcutRecord) {
+        match self.shortcuts.iter_mut().find(|shortcut| shortcut.path == record.path) {
+            Some(shortcut) => *shortcut = record,
+            None => self.shortcuts.push(record),
+        }
+    }
+
+    /// Removes the record of a shortcut.
+    pub fn remove(&mut self, path: &Path) {
+        self.shortcuts.retain(|shortcut| shortcut.path != path);
+    }
+
+    /// Returns the shortcuts recorded for an osu! directory.
+    pub fn for_osu_dir(&self, osu_dir: &Path) -> impl Iterator<Item=&ShortcutRecord> {
+        self.shortcuts
+            .iter()
+            .filter(move |shortcut| process::is_same_path(&*shortcut.osu_dir, osu_dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempOsuDir;
+
+    fn record(path: &str, server: &str, osu_dir: &str) -> ShortcutRecord {
+        ShortcutRecord {
+            path: PathBuf::from(path),
+            server: server.to_owned(),
+            osu_dir: PathBuf::from(osu_dir),
+            icon: format!("osu-switcher-{server}"),
+            target: PathBuf::from("/opt/osu!switcher/osu-switcher"),
+            args_format: 2,
+            wine_prefix: None,
+            version: "2.0.0".to_owned(),
+        }
+    }
+
+    #[test]
+    fn round_trips_shortcuts() {
+        let manifest = ShortcutManifest {
+            path: PathBuf::new(),
+            shortcuts: vec![
+                record("/home/tester/osu! (akatsuki.gg).desktop", "akatsuki.gg", "/games/osu!"),
+                record(r"C:\Users\tester\Desktop\osu! (ripple.moe).lnk", "ripple.moe", r"D:\osu!"),
+                ShortcutRecord {
+                    wine_prefix: Some(PathBuf::from("/games/osu-prefix")),
+                    ..record("/desktop/osu! (gatari.pw).desktop", "gatari.pw", "/games/osu!")
+                },
+            ],
+        };
+
+        let mut written = Vec::new();
+        manifest.to_ini().unwrap().write_to(&mut written).unwrap();
+        let ini = Ini::load_from_str(&*String::from_utf8(written).unwrap()).unwrap();
+
+        assert_eq!(ShortcutManifest::from_ini(&ini).unwrap(), manifest.shortcuts);
+    }
+
+    #[test]
+    fn replaces_shortcuts_at_same_path() {
+        let mut manifest = ShortcutManifest { path: PathBuf::new(), shortcuts: Vec::new() };
+        manifest.record(record("/desktop/osu! (gatari.pw).desktop", "gatari.pw", "/games/osu!"));
+        manifest.record(record("/desktop/osu! (gatari.pw).desktop", "gatari.pw", "/games/tourney"));
+
+        assert_eq!(manifest.shortcuts.len(), 1);
+        assert_eq!(manifest.for_osu_dir(Path::new("/games/osu!")).count(), 0);
+        assert_eq!(manifest.for_osu_dir(Path::new("/games/tourney")).count(), 1);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn finds_shortcuts_through_links() {
+        let osu_dir = TempOsuDir::new("manifest-links");
+        let link = osu_dir.with_extension("link");
+        let _ = fs::remove_file(&*link);
+        std::os::unix::fs::symlink(&*osu_dir, &*link).unwrap();
+
+        let mut manifest = ShortcutManifest { path: PathBuf::new(), shortcuts: Vec::new() };
+        let link_str = link.to_str().unwrap();
+        manifest.record(record("/desktop/osu! (gatari.pw).desktop", "gatari.pw", link_str));
+
+        assert_eq!(manifest.for_osu_dir(&*osu_dir).count(), 1);
+        fs::remove_file(&*link).unwrap();
+    }
+
+    #[test]
+    fn reads_shortcuts_without_target() {
+        let ini = Ini::load_from_str(
+            "Version=1\n\
+            [Shortcut]\n\
+            Path=/desktop/osu! (gatari.pw).desktop\n\
+            Server=gatari.pw\n\
+            OsuDir=/games/osu!\n\
+            Icon=osu-switcher-gatari.pw\n\
+            SwitcherVersion=2.0.0\n",
+        )
+        .unwrap();
+
+        let shortcuts = ShortcutManifest::from_ini(&ini).unwrap();
+        assert_eq!(shortcuts[0].target, PathBuf::new());
+        assert_eq!(shortcuts[0].args_format, 1);
+    }
+
+    #[test]
+    fn rejects_newer_manifests() {
+        let ini = Ini::load_from_str("Version=2\n").unwrap();
+        assert!(ShortcutManifest::from_ini(&ini).is_err());
+    }
+}
@@ -0,0 +1,507 @@
+use crate::launcher;
+use crate::osu_util::check_osu_installation;
+use crate::platform;
+use crate::platform::{Shortcut, PORTABLE_FILE_NAME, SHORTCUT_EXTENSION, SWITCHER_EXE};
+use crate::process;
+use crate::secrets::KEY_FILE_NAME;
+use crate::servers;
+use crate::shortcuts::manifest::{ShortcutManifest, ShortcutRecord};
+use color_eyre::eyre::{bail, Context, ContextCompat};
+use color_eyre::Result;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::{env, fs};
+
+pub mod manifest;
+
+/// The version of the arguments that shortcuts start the switcher with, which is passed along as
+/// `--shortcut-format`. This is bumped whenever they change, so outdated shortcuts can be detected.
+pub const ARGS_FORMAT: usize = 3;
+
+/// All the icons of private servers I could find.\
+/// They were converted into icon files with ImageMagick:
+/// ```shell
+/// for file in "./*.{svg,png,jpg,webp};" do
+///     magick -background transparent "$file" \
+///         -define icon:auto-resize=128,64,48,32,16 \
+///         -set option:wd "%[fx:(1/1)>(w/h)?(1/1*h):w]" \
+///         -set option:ht "%[fx:(1/1)>(w/h)?h:(w/(1/1))]" \
+///         -gravity center -background transparent -extent "%[wd]x%[ht]" \
+///         "../${file%.*}.ico";
+/// done
+/// ```
+#[cfg(windows)]
+static ICONS: LazyLock<HashMap<&'static str, &'static [u8]>> = LazyLock::new(|| HashMap::from([
+    // @formatter:off
+    ("akatsuki.gg", include_bytes!("../../assets/akatsuki.gg.ico").as_slice()),
+    ("ez-pp.farm", include_bytes!("../../assets/ez-pp.farm.ico").as_slice()),
+    ("gatari.pw", include_bytes!("../../assets/gatari.pw.ico").as_slice()),
+    ("halcyon.moe", include_bytes!("../../assets/halcyon.moe.ico").as_slice()),
+    ("kawata.pw", include_bytes!("../../assets/kawata.pw.ico").as_slice()),
+    ("kokisu.moe", include_bytes!("../../assets/kokisu.moe.ico").as_slice()),
+    ("lemres.de", include_bytes!("../../assets/lemres.de.ico").as_slice()),
+    ("localhost", include_bytes!("../../assets/localhost.ico").as_slice()),
+    ("mamesosu.net", include_bytes!("../../assets/mamesosu.net.ico").as_slice()),
+    ("osu.ppy.sb", include_bytes!("../../assets/osu.ppy.sb.ico").as_slice()),
+    ("osunolimits.dev", include_bytes!("../../assets/osunolimits.dev.ico").as_slice()),
+    ("redstar.moe", include_bytes!("../../assets/redstar.moe.ico").as_slice()),
+    ("ripple.moe", include_bytes!("../../assets/ripple.moe.ico").as_slice()),
+    ("scosu.net", include_bytes!("../../assets/scosu.net.ico").as_slice()),
+    ("seventwentyseven.xyz", include_bytes!("../../assets/seventwentyseven.xyz.ico").as_slice()),
+    ("ussr.pl", include_bytes!("../../assets/ussr.pl.ico").as_slice()),
+    // @formatter:on
+]));
+
+/// The original images of the same server icons, since desktop entries can't use icon files.
+/// These are keyed by server, along with the file extension of the image.
+#[cfg(target_os = "linux")]
+static ICONS: LazyLock<HashMap<&'static str, (&'static str, &'static [u8])>> = LazyLock::new(|| HashMap::from([
+    // @formatter:off
+    ("akatsuki.gg", ("png", include_bytes!("../../assets/original/akatsuki.gg.png").as_slice())),
+    ("ez-pp.farm", ("webp", include_bytes!("../../assets/original/ez-pp.farm.webp").as_slice())),
+    ("gatari.pw", ("png", include_bytes!("../../assets/original/gatari.pw.png").as_slice())),
+    ("halcyon.moe", ("png", include_bytes!("../../assets/original/halcyon.moe.png").as_slice())),
+    ("kawata.pw", ("png", include_bytes!("../../assets/original/kawata.pw.png").as_slice())),
+    ("kokisu.moe", ("png", include_bytes!("../../assets/original/kokisu.moe.png").as_slice())),
+    ("lemres.de", ("jpg", include_bytes!("../../assets/original/lemres.de.jpg").as_slice())),
+    ("localhost", ("png", include_bytes!("../../assets/original/localhost.png").as_slice())),
+    ("mamesosu.net", ("png", include_bytes!("../../assets/original/mamesosu.net.png").as_slice())),
+    ("osu.ppy.sb", ("png", include_bytes!("../../assets/original/osu.ppy.sb.png").as_slice())),
+    ("osunolimits.dev", ("png", include_bytes!("../../assets/original/osunolimits.dev.png").as_slice())),
+    ("redstar.moe", ("png", include_bytes!("../../assets/original/redstar.moe.png").as_slice())),
+    ("ripple.moe", ("svg", include_bytes!("../../assets/original/ripple.moe.svg").as_slice())),
+    ("scosu.net", ("png", include_bytes!("../../assets/original/scosu.net.png").as_slice())),
+    ("seventwentyseven.xyz", ("png", include_bytes!("../../assets/original/seventwentyseven.xyz.png").as_slice())),
+    ("ussr.pl", ("png", include_bytes!("../../assets/original/ussr.pl.png").as_slice())),
+    // @formatter:on
+]));
+
+/// Returns all known osu! server domains.
+pub fn known_servers() -> Vec<String> {
+    let mut known_servers = vec![servers::BANCHO.to_owned()];
+    known_servers.extend(ICONS.keys().map(|s| (*s).to_owned()));
+    known_servers.sort_unstable();
+    known_servers
+}
+
+/// Returns the path to the osu! logo to be used as a shortcut icon.
+/// This resolves to the osu! executable.
+#[cfg(windows)]
+fn osu_server_icon(osu_dir: &Path) -> PathBuf {
+    osu_dir.join("osu!.exe")
+}
+
+/// Writes a server icon shipped with this executable to the osu! directory
+/// to be used as shortcut icons, since they need to be on disk.
+#[cfg(windows)]
+fn write_server_icon(osu_dir: &Path, server: &str) -> Result<String> {
+    let icons_dir = osu_dir.join("icons");
+    let icon_path = icons_dir.join(format!("{server}.ico"));
+
+    let icon_path = if server == servers::BANCHO {
+        // Fast path
+        osu_server_icon(osu_dir)
+    } else if let Some(bytes) = ICONS.get(server) {
+        fs::create_dir_all(&*icons_dir)
+            .with_context(|| format!("failed to create icons directory {icons_dir:?}"))?;
+        fs::write(&*icon_path, bytes)
+            .with_context(|| format!("failed to write server icon to disk {icons_dir:?}"))?;
+
+        icon_path
+    } else {
+        osu_server_icon(osu_dir)
+    };
+
+    icon_path
+        .to_str()
+        .context("icon path contains invalid characters")
+        .map(str::to_owned)
+}
+
+/// Returns the paths of every server icon that might have been written for an osu! directory.
+#[cfg(windows)]
+pub fn icon_paths(osu_dir: &Path) -> Result<Vec<PathBuf>> {
+    let icons_dir = osu_dir.join("icons");
+    Ok(ICONS.keys().map(|server| icons_dir.join(format!("{server}.ico"))).collect())
+}
+
+/// The icon theme name of the icon used for servers without an icon, since osu! has none on Linux.
+#[cfg(target_os = "linux")]
+const GENERIC_ICON: &str = "applications-games";
+
+/// Returns where the icon of a server gets installed, and whether that is in the icon theme.
+/// Icon themes only support PNG and SVG icons, so any other images go into the osu! directory.
+#[cfg(target_os = "linux")]
+fn server_icon_path(osu_dir: &Path, server: &str, extension: &str) -> Result<(PathBuf, bool)> {
+    let icon_name = format!("osu-switcher-{server}");
+
+    Ok(match extension {
+        "png" => (
+            platform::icon_theme_dir()?.join(format!("256x256/apps/{icon_name}.png")),
+            true,
+        ),
+        "svg" => (
+            platform::icon_theme_dir()?.join(format!("scalable/apps/{icon_name}.svg")),
+            true,
+        ),
+        _ => (osu_dir.join(format!("icons/{server}.{extension}")), false),
+    })
+}
+
+/// Installs a server icon shipped with this executable, and returns its icon name if it is in the
+/// icon theme, or otherwise its path.
+#[cfg(target_os = "linux")]
+fn write_server_icon(osu_dir: &Path, server: &str) -> Result<String> {
+    let Some((extension, bytes)) = ICONS.get(server) else { return Ok(GENERIC_ICON.to_owned()) };
+    let (icon_path, themed) = server_icon_path(osu_dir, server, extension)?;
+
+    let icons_dir = icon_path.parent().context("icon path has no parent")?;
+    fs::create_dir_all(icons_dir)
+        .with_context(|| format!("failed to create icons directory {icons_dir:?}"))?;
+    fs::write(&*icon_path, bytes)
+        .with_context(|| format!("failed to write server icon to disk {icon_path:?}"))?;
+
+    let icon = if themed { icon_path.file_stem() } else { Some(icon_path.as_os_str()) };
+    icon.and_then(|icon| icon.to_str())
+        .context("icon path contains invalid characters")
+        .map(str::to_owned)
+}
+
+/// Returns the paths of every server icon that might have been written for an osu! directory.
+#[cfg(target_os = "linux")]
+pub fn icon_paths(osu_dir: &Path) -> Result<Vec<PathBuf>> {
+    ICONS
+        .iter()
+        .map(|(server, (extension, _))| {
+            server_icon_path(osu_dir, server, extension).map(|(icon_path, _)| icon_path)
+        })
+        .collect()
+}
+
+/// Returns every shortcut that was created (such as on the user's Desktop),
+/// along with the server it is for.
+pub fn find_shortcuts() -> Result<Vec<(PathBuf, String)>> {
+    let suffix = format!(").{SHORTCUT_EXTENSION}");
+    let mut shortcuts = Vec::new();
+
+    for shortcut_dir in platform::shortcut_dirs()? {
+        let entries = fs::read_dir(&*shortcut_dir)
+            .with_context(|| format!("failed to list shortcuts {shortcut_dir:?}"))?;
+
+        shortcuts.extend(entries.filter_map(|entry| entry.ok()).filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let server = name.strip_prefix("osu! (")?.strip_suffix(&*suffix)?;
+            Some((entry.path(), server.to_owned()))
+        }));
+    }
+
+    Ok(shortcuts)
+}
+
+/// Returns the servers that shortcuts were created for in an osu! directory, according to the
+/// shortcut manifest.
+pub fn created_servers(osu_dir: &Path) -> Result<Vec<String>> {
+    let manifest = ShortcutManifest::load(&*platform::install_dir(osu_dir)?)?;
+    Ok(manifest_servers(&manifest, osu_dir))
+}
+
+/// Returns whether the manifest records a shortcut to a server in an osu! directory that was
+/// created with an older argument format. Shortcuts recorded before the format was introduced
+/// are always outdated.
+pub fn has_outdated_shortcut(osu_dir: &Path, server: &str) -> bool {
+    let Ok(install_dir) = platform::install_dir(osu_dir) else { return false };
+    let Ok(manifest) = ShortcutManifest::load(&*install_dir) else { return false };
+
+    manifest
+        .for_osu_dir(osu_dir)
+        .any(|shortcut| shortcut.server == server && shortcut.args_format < ARGS_FORMAT)
+}
+
+/// Returns the servers that shortcuts are recorded for in an osu! directory.
+fn manifest_servers(manifest: &ShortcutManifest, osu_dir: &Path) -> Vec<String> {
+    let mut servers: Vec<String> = manifest
+        .for_osu_dir(osu_dir)
+        .map(|shortcut| shortcut.server.clone())
+        .collect();
+
+    servers.sort_unstable();
+    servers.dedup();
+    servers
+}
+
+/// Creates shortcuts (such as on the user's Desktop) to this osu!switcher binary that trigger
+/// an auth switch to a different osu! private server, and records them in the manifest.
+/// Recorded shortcuts that are still up to date are left as is.
+fn create_shortcut(
+    manifest: &mut ShortcutManifest,
+    osu_dir: &Path,
+    switcher_path: &Path,
+    server: &str,
+) -> Result<()> {
+    let name = format!("osu! ({server})");
+    let osu_dir = osu_dir
+        .to_str()
+        .context("osu! install directory contains invalid characters")?;
+
+    let icon = write_server_icon(Path::new(osu_dir), server)?;
+
+    // Desktop launchers don't pass WINEPREFIX along, so the shortcut needs to know the prefix
+    let wine_prefix = if cfg!(target_os = "linux") {
+        let wine_prefix = env::var("WINEPREFIX").ok();
+        launcher::find_prefix(Path::new(osu_dir), wine_prefix.as_deref())
+    } else {
+        None
+    };
+    let wine_prefix_str = wine_prefix
+        .as_deref()
+        .map(|prefix| prefix.to_str().context("Wine prefix contains invalid characters"))
+        .transpose()?;
+
+    for shortcut_dir in platform::shortcut_dirs()? {
+        let link_path = shortcut_dir.join(&*format!("{name}.{SHORTCUT_EXTENSION}"));
+        let args_format = ARGS_FORMAT.to_string();
+        let mut args = vec!["switch", "--shortcut-format", &*args_format];
+
+        // Portable switchers find osu! next to themselves, so the shortcut works from any drive
+        if switcher_path.parent() != Some(Path::new(osu_dir)) {
+            args.extend(["--osu", osu_dir]);
+        }
+        if let Some(wine_prefix) = wine_prefix_str {
+            args.extend(["--wine-prefix", wine_prefix]);
+        }
+        args.extend(["--server", server]);
+
+        let record = ShortcutRecord {
+            path: link_path.clone(),
+            server: server.to_owned(),
+            osu_dir: PathBuf::from(osu_dir),
+            icon: icon.clone(),
+            target: switcher_path.to_owned(),
+            args_format: ARGS_FORMAT,
+            wine_prefix: wine_prefix.clone(),
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+        };
+
+        if manifest.get(&*link_path) == Some(&record) && fs::exists(&*link_path).unwrap_or(false) {
+            continue;
+        }
+
+        if fs::exists(&*link_path).unwrap_or(false) {
+            fs::remove_file(&link_path)
+                .with_context(|| format!("failed to delete old shortcut {link_path:?}"))?;
+        }
+
+        platform::create_shortcut(
+            &*link_path,
+            &Shortcut {
+                name: &*name,
+                target: switcher_path,
+                args: &*args,
+                icon: &*icon,
+            },
+        )?;
+        manifest.record(record);
+    }
+
+    Ok(())
+}
+
+/// Forgets about recorded shortcuts that were deleted since, and with `prune`, deletes the
+/// shortcuts created for an osu! directory that aren't for any of the specified servers.
+fn prune_shortcuts(
+    manifest: &mut ShortcutManifest,
+    osu_dir: &Path,
+    servers: &[String],
+    prune: bool,
+) -> Result<()> {
+    let stale: Vec<PathBuf> = manifest
+        .shortcuts
+        .iter()
+        .filter(|shortcut| {
+            !fs::exists(&*shortcut.path).unwrap_or(false)
+                || prune
+                    && process::is_same_path(&*shortcut.osu_dir, osu_dir)
+                    && !servers.contains(&shortcut.server)
+        })
+        .map(|shortcut| shortcut.path.clone())
+        .collect();
+
+    for path in stale {
+        if fs::exists(&*path).unwrap_or(false) {
+            fs::remove_file(&*path)
+                .with_context(|| format!("failed to delete old shortcut {path:?}"))?;
+        }
+        manifest.remove(&*path);
+    }
+
+    Ok(())
+}
+
+/// Points every shortcut created for an osu! directory at its new location, writing the server
+/// icons there as well. Returns the servers of the relocated shortcuts.
+pub fn relocate(from: &Path, to: &Path) -> Result<Vec<String>> {
+    // Portable installations were moved along with osu!
+    let install_dir = platform::install_dir(to)?;
+    let servers = manifest_servers(&ShortcutManifest::load(&*install_dir)?, from);
+    install(to, servers.iter().map(|server| &**server), false, false)?;
+
+    // Shortcuts that weren't rewritten in place (such as on a Desktop that moved since) are gone
+    let mut manifest = ShortcutManifest::load(&*install_dir)?;
+    prune_shortcuts(&mut manifest, from, &[], true)?;
+    manifest.save()?;
+
+    Ok(servers)
+}
+
+/// The file name of the README that explains the installation location of this switcher.
+pub const README_FILE_NAME: &str = "README.txt";
+
+/// The file name of the version stamp of the switcher executable in the installation location.
+pub const VERSION_FILE_NAME: &str = "osu!switcher.version";
+
+/// Marks the switcher installed into an osu! directory as portable, which moves all of its state
+/// there. The encryption key is copied along, since the stored sessions are encrypted with it.
+fn make_portable(osu_dir: &Path) -> Result<()> {
+    let portable_marker = osu_dir.join(PORTABLE_FILE_NAME);
+    if fs::exists(&*portable_marker).unwrap_or(false) {
+        return Ok(());
+    }
+
+    let key_path = platform::default_install_dir()?.join(KEY_FILE_NAME);
+    let portable_key_path = osu_dir.join(KEY_FILE_NAME);
+    if fs::exists(&*key_path).unwrap_or(false)
+        && !fs::exists(&*portable_key_path).unwrap_or(false)
+    {
+        fs::copy(&*key_path, &*portable_key_path)
+            .with_context(|| format!("failed to copy encryption key to {portable_key_path:?}"))?;
+    }
+
+    fs::write(&*portable_marker, "osu!switcher keeps all of its state in this directory.\n")
+        .with_context(|| format!("failed to write portable marker {portable_marker:?}"))
+}
+
+/// Returns whether a version (such as `2.1.0`) is newer than another one.
+/// Unparsable versions are treated as older than anything else.
+fn is_newer_version(version: &str, than: &str) -> bool {
+    let parse = |version: &str| -> Option<Vec<u64>> {
+        version.trim().split('.').map(|part| part.parse().ok()).collect()
+    };
+
+    parse(version) > parse(than)
+}
+
+/// Re-creates the recorded shortcuts that were created by a different version of this switcher,
+/// or that start a different executable or arguments than it would now.
+fn upgrade_shortcuts(manifest: &mut ShortcutManifest, switcher_path: &Path) -> Result<()> {
+    let outdated: Vec<(PathBuf, String)> = manifest
+        .shortcuts
+        .iter()
+        .filter(|shortcut| {
+            shortcut.version != env!("CARGO_PKG_VERSION")
+                || shortcut.target != switcher_path
+                || shortcut.args_format != ARGS_FORMAT
+        })
+        .map(|shortcut| (shortcut.osu_dir.clone(), shortcut.server.clone()))
+        .collect();
+
+    for (osu_dir, server) in outdated {
+        // osu! might have been moved or uninstalled since
+        if check_osu_installation(&*osu_dir) {
+            create_shortcut(manifest, &*osu_dir, switcher_path, &*server)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Installs this switcher in a permanent location and creates the specified server shortcuts.
+/// With `prune`, the shortcuts previously created for this osu! directory are replaced entirely,
+/// deleting those for any other servers. With `portable`, the switcher is installed into the osu!
+/// directory itself instead, which stays that way for later installs.
+pub fn install<'a, S>(osu_dir: &Path, servers: S, prune: bool, portable: bool) -> Result<()>
+where
+    S: IntoIterator<Item=&'a str>,
+{
+    let this_exe = env::current_exe()
+        .context("failed to get path to current running executable")?;
+    let install_dir = if portable {
+        osu_dir.to_owned()
+    } else {
+        platform::install_dir(osu_dir)?
+    };
+
+    let this_version = env!("CARGO_PKG_VERSION");
+    let version_path = install_dir.join(VERSION_FILE_NAME);
+
+    // Install self to permanent location
+    let installed_exe = if !this_exe.starts_with(&*install_dir) {
+        let new_exe = install_dir.join(SWITCHER_EXE);
+        let readme_exe = install_dir.join(README_FILE_NAME);
+
+        // Executables installed before the version stamp existed are always older
+        if fs::exists(&*new_exe).unwrap_or(false)
+            && let Ok(installed_version) = fs::read_to_string(&*version_path)
+        {
+            let installed_version = installed_version.trim();
+
+            if is_newer_version(installed_version, this_version) {
+                bail!(
+                    "a newer osu!switcher ({installed_version}) is already installed, \
+                    run {new_exe:?} instead of this one ({this_version})"
+                );
+            }
+            if is_newer_version(this_version, installed_version) {
+                println!("Upgrading osu!switcher from {installed_version} to {this_version}");
+            }
+        }
+
+        let readme_banner = format!("\
+        This is the permanent installation location of osu!switcher (https://github.com/rushiiMachine/osu-switcher).\n\
+        The '{SWITCHER_EXE}' executable is referenced by the osu! shortcuts generated onto the desktop.\n\
+        ");
+
+        fs::create_dir_all(&*install_dir)
+            .with_context(|| format!("failed to create installation dir {install_dir:?}"))?;
+        fs::copy(&*this_exe, &*new_exe)
+            .with_context(|| format!("failed to copy current executable to installation dir {new_exe:?}"))?;
+        fs::write(&*readme_exe, readme_banner)?;
+
+        new_exe
+    } else {
+        this_exe
+    };
+
+    fs::write(&*version_path, this_version)
+        .with_context(|| format!("failed to write version stamp {version_path:?}"))?;
+
+    if portable {
+        make_portable(osu_dir)?;
+    }
+
+    let servers: Vec<String> = servers.into_iter().map(servers::canonicalize).collect();
+    let mut manifest = ShortcutManifest::load(&*install_dir)?;
+
+    prune_shortcuts(&mut manifest, osu_dir, &*servers, prune)?;
+    upgrade_shortcuts(&mut manifest, &*installed_exe)?;
+    for server in &servers {
+        create_shortcut(&mut manifest, osu_dir, &*installed_exe, server)?;
+    }
+
+    manifest.save()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_versions() {
+        assert!(is_newer_version("2.1.0", "2.0.0"));
+        assert!(is_newer_version("2.10.0", "2.9.1"));
+        assert!(is_newer_version("3.0.0\n", "2.0.0"));
+        assert!(!is_newer_version("2.0.0", "2.0.0"));
+        assert!(!is_newer_version("1.9.9", "2.0.0"));
+        assert!(!is_newer_version("garbage", "2.0.0"));
+    }
+}
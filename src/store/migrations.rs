@@ -0,0 +1,220 @@
+use crate::servers;
+use crate::store::SwitcherStore;
+use color_eyre::eyre::{bail, Context};
+use color_eyre::Result;
+use ini::Ini;
+
+/// The version of the switcher config layout written by this switcher.
+pub const VERSION: u32 = 2;
+
+/// The key in the general section of the switcher config that holds its layout version.
+/// Configs without one predate versioning, and are treated as version 0.
+const VERSION_KEY: &str = "Version";
+
+/// A single upgrade of the switcher config, from the version before it to its own version.
+pub struct Migration {
+    /// The config version after applying this migration.
+    pub version: u32,
+    pub description: &'static str,
+    apply: fn(&mut Ini),
+}
+
+/// Every migration, ordered by version.
+///
+/// Configs stored under the legacy file name (`server-account-switcher.ini`) are always version 0,
+/// and get renamed by [SwitcherStore] when migrating them.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "store every account in its own section",
+        apply: split_accounts,
+    },
+    Migration {
+        version: 2,
+        description: "move the sessions of servers that changed domains",
+        apply: move_domains,
+    },
+];
+
+/// Reads the layout version of a switcher config.
+pub fn version(ini: &Ini) -> Result<u32> {
+    match ini.get_from(None::<&str>, VERSION_KEY) {
+        Some(version) => version.parse().context("switcher config has an invalid version"),
+        None => Ok(0),
+    }
+}
+
+/// Applies every migration newer than the version of a switcher config in order,
+/// and updates its version. Returns the migrations that were applied.
+pub fn migrate(ini: &mut Ini) -> Result<Vec<&'static Migration>> {
+    let version = version(ini)?;
+
+    if version > VERSION {
+        bail!(
+            "the switcher config was written by a newer version of osu!switcher \
+            (config version {version}), please update first"
+        );
+    }
+
+    let pending: Vec<&Migration> = MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > version)
+        .collect();
+
+    for migration in &pending {
+        (migration.apply)(ini);
+    }
+
+    // Servers can gain new aliases after a config was migrated, so always move their sessions
+    move_domains(ini);
+
+    ini.with_general_section().set(VERSION_KEY, VERSION.to_string());
+    Ok(pending)
+}
+
+/// Converts the old layout of one `[server]` section holding the credentials directly
+/// into an account section for that server.
+fn split_accounts(ini: &mut Ini) {
+    let legacy_servers: Vec<String> = ini
+        .iter()
+        .filter_map(|(name, section)| {
+            let name = name?;
+            let is_legacy = SwitcherStore::parse_account_section(name).is_none()
+                && section.contains_key("Username");
+
+            is_legacy.then(|| name.to_owned())
+        })
+        .collect();
+
+    for server in legacy_servers {
+        let Some(section) = ini.delete(Some(&*server)) else { continue };
+        let username = section.get("Username").unwrap_or("");
+
+        // Accounts without a username cannot be identified
+        if username.is_empty() {
+            continue;
+        }
+
+        ini.with_section(Some(SwitcherStore::account_section(&*server, username)))
+            .set("Username", username)
+            .set("Password", section.get("Password").unwrap_or(""));
+        ini.with_section(Some(&*server)).set("Account", username);
+    }
+}
+
+/// Moves the accounts stored under an alias of a server to its canonical domain.
+/// Accounts that are already stored for the canonical domain are kept as is.
+fn move_domains(ini: &mut Ini) {
+    for (old_server, new_server) in servers::ALIASES {
+        let accounts: Vec<String> = ini
+            .sections()
+            .flatten()
+            .filter_map(|name| {
+                let (username, server) = SwitcherStore::parse_account_section(name)?;
+                (server == *old_server).then_some(username)
+            })
+            .collect();
+
+        for username in accounts {
+            let old_section = SwitcherStore::account_section(old_server, &*username);
+            let new_section = SwitcherStore::account_section(new_server, &*username);
+            let Some(section) = ini.delete(Some(old_section)) else { continue };
+
+            ini.entry(Some(new_section)).or_insert(section);
+        }
+
+        if let Some(section) = ini.delete(Some(*old_server)) {
+            ini.entry(Some((*new_server).to_owned())).or_insert(section);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secrets::KeyFileProvider;
+    use crate::switcher::plan::ValueChange;
+    use crate::test_util::TempOsuDir;
+    use std::fs;
+
+    fn load_fixture(contents: &str) -> Ini {
+        Ini::load_from_str(contents).unwrap()
+    }
+
+    fn assert_migrates_to(input: &str, expected: &str) {
+        let mut ini = load_fixture(input);
+        migrate(&mut ini).unwrap();
+
+        let changes = ValueChange::diff_ini(&load_fixture(expected), &ini);
+        assert!(changes.is_empty(), "unexpected changes: {changes:#?}");
+    }
+
+    #[test]
+    fn migrations_are_ordered() {
+        let versions: Vec<u32> = MIGRATIONS.iter().map(|migration| migration.version).collect();
+
+        assert_eq!(versions, (1..=VERSION).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn migrates_legacy_layout() {
+        assert_migrates_to(
+            include_str!("../../tests/fixtures/store/v0_legacy_layout.ini"),
+            include_str!("../../tests/fixtures/store/v0_legacy_layout.expected.ini"),
+        );
+    }
+
+    #[test]
+    fn migrates_domain_moves() {
+        assert_migrates_to(
+            include_str!("../../tests/fixtures/store/v1_domain_moves.ini"),
+            include_str!("../../tests/fixtures/store/v1_domain_moves.expected.ini"),
+        );
+    }
+
+    #[test]
+    fn moves_aliases_at_current_version() {
+        assert_migrates_to(
+            include_str!("../../tests/fixtures/store/v2_domain_aliases.ini"),
+            include_str!("../../tests/fixtures/store/v2_domain_aliases.expected.ini"),
+        );
+    }
+
+    #[test]
+    fn current_version_is_unchanged() {
+        let contents = include_str!("../../tests/fixtures/store/v2_current.ini");
+        let mut ini = load_fixture(contents);
+
+        assert!(migrate(&mut ini).unwrap().is_empty());
+        assert!(ValueChange::diff_ini(&load_fixture(contents), &ini).is_empty());
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        let mut ini = load_fixture("Version=999\n");
+
+        assert!(migrate(&mut ini).is_err());
+    }
+
+    #[test]
+    fn backs_up_legacy_config() {
+        let osu_dir = TempOsuDir::new("legacy-backup");
+        let legacy = include_str!("../../tests/fixtures/store/v0_legacy_layout.ini");
+        fs::write(osu_dir.join("server-account-switcher.ini"), legacy).unwrap();
+
+        let secrets = Box::new(KeyFileProvider::new(osu_dir.join("test.key")));
+        let store = SwitcherStore::load_with(&*osu_dir, secrets).unwrap();
+        store.save().unwrap();
+        store.verify().unwrap();
+
+        let backup = fs::read_to_string(osu_dir.join("osu!switcher.ini.v0.bak")).unwrap();
+        assert_eq!(backup, legacy);
+        assert!(!fs::exists(osu_dir.join("server-account-switcher.ini")).unwrap());
+
+        // Migrations only run once
+        let secrets = Box::new(KeyFileProvider::new(osu_dir.join("test.key")));
+        let store = SwitcherStore::load_with(&*osu_dir, secrets).unwrap();
+        assert!(store.backup_path().is_none());
+        assert!(store.changes().is_empty());
+    }
+}
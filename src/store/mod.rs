@@ -0,0 +1,576 @@
+use crate::secrets;
+use crate::secrets::SecretProvider;
+use crate::store::migrations::Migration;
+use crate::switcher::plan::ValueChange;
+use color_eyre::eyre::{bail, Context};
+use color_eyre::Result;
+use ini::Ini;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub mod migrations;
+pub mod settings;
+
+/// Authentication details for a single account on a single server.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AuthDetails {
+    pub username: String,
+    pub password: String,
+    pub server: String,
+}
+
+/// The osu!switcher config (`osu!switcher.ini`) that stores the auth details of every account
+/// that has been switched away from.
+///
+/// Each stored account lives in its own `[username@server]` section, while a `[server]` section
+/// records which of that server's accounts was used last. Session keys are encrypted on disk,
+/// but kept decrypted in memory. Session keys that can't be decrypted are kept as they are,
+/// so that they can still be used if the key comes back. The layout version is stored in
+/// the general section.
+pub struct SwitcherStore {
+    path: PathBuf,
+    /// The legacy config file this store was loaded from, which gets renamed upon saving.
+    legacy_path: Option<PathBuf>,
+    /// Where the config on disk gets backed up to upon saving, if it was migrated.
+    backup_path: Option<PathBuf>,
+    /// The migrations that were applied to the config on disk.
+    migrations: Vec<&'static Migration>,
+    /// Provides the key that session keys are encrypted with.
+    secrets: Box<dyn SecretProvider>,
+    /// Whether any session keys were stored in plain text, which get encrypted upon saving.
+    has_plaintext: bool,
+    /// The account sections whose session keys could not be decrypted, which are treated as
+    /// having no session and written back unchanged.
+    locked: HashSet<String>,
+    /// The config as it was on disk, before any changes.
+    original: Ini,
+    ini: Ini,
+}
+
+impl SwitcherStore {
+    /// The file name of the switcher config inside the osu! directory.
+    pub const FILE_NAME: &'static str = "osu!switcher.ini";
+
+    /// The file name of the switcher config used by older versions of this switcher.
+    const LEGACY_FILE_NAME: &'static str = "server-account-switcher.ini";
+
+    /// Characters of usernames that are percent-encoded in account section names.
+    const RESERVED_CHARS: &'static [char] = &['%', '[', ']', '=', ';', '#', '@', '\\'];
+
+    /// Lists every file of the switcher config in an osu! directory, including legacy ones and
+    /// the backups made before migrating them.
+    pub fn files(osu_dir: &Path) -> Result<Vec<PathBuf>> {
+        let entries = fs::read_dir(osu_dir)
+            .with_context(|| format!("failed to list osu! directory {osu_dir:?}"))?;
+
+        let files = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                let is_backup = name
+                    .strip_prefix(Self::FILE_NAME)
+                    .is_some_and(|suffix| suffix.starts_with(".v") && suffix.ends_with(".bak"));
+
+                name == Self::FILE_NAME || name == Self::LEGACY_FILE_NAME || is_backup
+            })
+            .map(|entry| entry.path())
+            .collect();
+
+        Ok(files)
+    }
+
+    /// Loads the switcher config from an osu! directory, migrating it if needed.
+    /// Nothing is written to disk until the store is saved.
+    pub fn load(osu_dir: &Path) -> Result<Self> {
+        Self::load_with(osu_dir, secrets::default_provider(osu_dir)?)
+    }
+
+    /// Loads the switcher config with a specific provider for the encryption key.
+    pub fn load_with(osu_dir: &Path, secrets: Box<dyn SecretProvider>) -> Result<Self> {
+        let path = osu_dir.join(Self::FILE_NAME);
+        let legacy_path = osu_dir.join(Self::LEGACY_FILE_NAME);
+
+        // Fall back to the legacy switcher config, which gets renamed upon saving
+        let legacy_path = (!fs::exists(&*path).unwrap_or(false)
+            && fs::exists(&*legacy_path).unwrap_or(false))
+        .then_some(legacy_path);
+
+        let file = legacy_path.as_deref().unwrap_or(&*path);
+        let mut ini = if fs::exists(file).context("checking osu!switcher config")? {
+            Ini::load_from_file(file)
+                .with_context(|| format!("failed loading osu!switcher config {file:?}"))?
+        } else {
+            Ini::new()
+        };
+        let (has_plaintext, locked) = Self::decrypt_passwords(&mut ini, &*secrets);
+        for (account, reason) in &locked {
+            println!(
+                "Warning: the stored session of {account} could not be decrypted ({reason}), \
+                sign in again after switching to it"
+            );
+        }
+        let locked = locked.into_iter().map(|(account, _)| account).collect();
+        let original = ini.clone();
+
+        let version = migrations::version(&ini)?;
+        let migrations = migrations::migrate(&mut ini)?;
+
+        // Configs that don't exist yet have nothing to back up
+        let backup_path = (!migrations.is_empty() && fs::exists(file).unwrap_or(false))
+            .then(|| osu_dir.join(format!("{0}.v{version}.bak", Self::FILE_NAME)));
+
+        Ok(Self {
+            path,
+            legacy_path,
+            backup_path,
+            migrations,
+            secrets,
+            has_plaintext,
+            locked,
+            original,
+            ini,
+        })
+    }
+
+    /// The path to the switcher config file.
+    pub fn path(&self) -> &Path {
+        &*self.path
+    }
+
+    /// The legacy switcher config file that will be migrated upon saving, if any.
+    pub fn legacy_path(&self) -> Option<&Path> {
+        self.legacy_path.as_deref()
+    }
+
+    /// Where the old switcher config will be backed up to upon saving, if it is being migrated.
+    pub fn backup_path(&self) -> Option<&Path> {
+        self.backup_path.as_deref()
+    }
+
+    /// The migrations that will be written upon saving.
+    pub fn migrations(&self) -> &[&'static Migration] {
+        &*self.migrations
+    }
+
+    /// Whether session keys stored in plain text will be encrypted upon saving.
+    pub fn has_plaintext(&self) -> bool {
+        self.has_plaintext
+    }
+
+    /// Returns every value that differs between the config on disk and this store.
+    pub fn changes(&self) -> Vec<ValueChange> {
+        ValueChange::diff_ini(&self.original, &self.ini)
+    }
+
+    /// Writes the switcher config back to disk. If it was migrated, then the old config is
+    /// backed up first, and the legacy config is moved to the backup if it was used.
+    pub fn save(&self) -> Result<()> {
+        let key = self.secrets.get_or_create_key()?;
+        let mut ini = self.ini.clone();
+
+        for (name, section) in ini.iter_mut() {
+            if let Some(password) = section.get("Password")
+                && !password.is_empty()
+                && !name.is_some_and(|name| self.locked.contains(name))
+            {
+                let encrypted = secrets::encrypt(&key, password)?;
+                section.insert("Password", encrypted);
+            }
+        }
+
+        if let Some(backup_path) = &self.backup_path {
+            let result = match &self.legacy_path {
+                Some(legacy_path) => fs::rename(legacy_path, backup_path),
+                None => fs::copy(&*self.path, backup_path).map(|_| ()),
+            };
+            result.with_context(|| format!("failed to back up switcher config {backup_path:?}"))?;
+        }
+
+        ini.write_to_file(&*self.path)
+            .context("failed to write switcher config")?;
+
+        if let Some(legacy_path) = &self.legacy_path
+            && self.backup_path.is_none()
+        {
+            fs::remove_file(legacy_path).context("failed migrating old switcher config")?;
+        }
+
+        Ok(())
+    }
+
+    /// Re-reads the switcher config from disk to ensure it matches this store.
+    pub fn verify(&self) -> Result<()> {
+        let mut written =
+            Ini::load_from_file(&*self.path).context("failed to re-read switcher config")?;
+        let (_, locked) = Self::decrypt_passwords(&mut written, &*self.secrets);
+
+        if locked.iter().any(|(account, _)| !self.locked.contains(account)) {
+            bail!("switcher config {0:?} could not be decrypted after writing it", self.path);
+        }
+        if !ValueChange::diff_ini(&self.ini, &written).is_empty() {
+            bail!("switcher config {0:?} does not match what was written", self.path);
+        }
+        if self.legacy_path.as_deref().is_some_and(|path| fs::exists(path).unwrap_or(true)) {
+            bail!("old switcher config still exists after migrating it");
+        }
+        if self.backup_path.as_deref().is_some_and(|path| !fs::exists(path).unwrap_or(false)) {
+            bail!("switcher config backup is missing after migrating it");
+        }
+
+        Ok(())
+    }
+
+    /// Finds the stored auth details for an account on a server.
+    /// If no account is specified, then the last used account for that server is picked.
+    pub fn get(&self, server: &str, account: Option<&str>) -> Option<AuthDetails> {
+        let username = match account {
+            Some(account) => account,
+            None => self.last_account(server)?,
+        };
+
+        let name = Self::account_section(server, username);
+        let section = self.ini.section(Some(&*name))?;
+
+        Some(AuthDetails {
+            username: section.get("Username").unwrap_or(username).to_owned(),
+            password: self.password(&*name, section.get("Password")),
+            server: server.to_owned(),
+        })
+    }
+
+    /// Stores the auth details of an account and marks it as the last used one for its server.
+    /// Accounts without a username cannot be identified, and are ignored.
+    pub fn put(&mut self, auth: &AuthDetails) {
+        if auth.username.is_empty() {
+            return;
+        }
+
+        let section = Self::account_section(&*auth.server, &*auth.username);
+        self.ini
+            .with_section(Some(&*section))
+            .set("Username", &*auth.username)
+            .set("Password", &*auth.password);
+        self.locked.remove(&section);
+        self.set_last_account(&*auth.server, &*auth.username);
+    }
+
+    /// Removes every stored account of a server, or only a single one if specified.
+    /// Returns the accounts that were removed.
+    pub fn forget(&mut self, server: &str, account: Option<&str>) -> Vec<AuthDetails> {
+        let removed: Vec<AuthDetails> = self
+            .accounts()
+            .into_iter()
+            .filter(|auth| auth.server == server)
+            .filter(|auth| account.is_none_or(|account| auth.username == account))
+            .collect();
+
+        for auth in &removed {
+            let section = Self::account_section(server, &*auth.username);
+            self.ini.delete(Some(&*section));
+            self.locked.remove(&section);
+        }
+
+        // Point the last used account to one that still exists
+        let remaining = self.accounts().into_iter().find(|auth| auth.server == server);
+        let last_removed = self
+            .last_account(server)
+            .is_none_or(|last| removed.iter().any(|auth| auth.username == last));
+
+        match remaining {
+            None => {
+                self.ini.delete(Some(server));
+            }
+            Some(remaining) if last_removed => {
+                self.set_last_account(server, &*remaining.username);
+            }
+            Some(_) => {}
+        }
+
+        removed
+    }
+
+    /// Moves every stored account of a server to a different server.
+    /// Fails without changing anything if an account already exists on the new server.
+    /// Returns the amount of accounts that were moved.
+    pub fn move_server(&mut self, old_server: &str, new_server: &str) -> Result<usize> {
+        // Session keys that can't be decrypted are moved as they are
+        let moved: Vec<AuthDetails> = self
+            .stored_accounts()
+            .filter(|auth| auth.server == old_server)
+            .collect();
+
+        for auth in &moved {
+            if self.get(new_server, Some(&*auth.username)).is_some() {
+                bail!("the account {0} is already stored for {new_server}", auth.username);
+            }
+        }
+
+        // Keep the new server's last used account if it already has one
+        let last_account = self
+            .last_account(new_server)
+            .or(self.last_account(old_server))
+            .map(str::to_owned);
+
+        let locked: Vec<bool> = moved
+            .iter()
+            .map(|auth| {
+                let section = Self::account_section(old_server, &*auth.username);
+                self.locked.contains(&section)
+            })
+            .collect();
+
+        self.forget(old_server, None);
+        for (auth, locked) in moved.iter().zip(locked) {
+            self.put(&AuthDetails {
+                server: new_server.to_owned(),
+                ..auth.clone()
+            });
+            if locked {
+                self.locked.insert(Self::account_section(new_server, &*auth.username));
+            }
+        }
+
+        // Putting accounts marks them as last used, so restore whichever was last used before
+        if let Some(last_account) = last_account {
+            self.set_last_account(new_server, &*last_account);
+        }
+
+        Ok(moved.len())
+    }
+
+    /// Returns the auth details of every stored account, in file order.
+    pub fn accounts(&self) -> Vec<AuthDetails> {
+        self.ini
+            .iter()
+            .filter_map(|(name, section)| {
+                let name = name?;
+                let (username, server) = Self::parse_account_section(name)?;
+
+                Some(AuthDetails {
+                    username: section.get("Username").map_or(username, str::to_owned),
+                    password: self.password(name, section.get("Password")),
+                    server: server.to_owned(),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the auth details of every stored account as they are stored, including the
+    /// session keys that could not be decrypted.
+    fn stored_accounts(&self) -> impl Iterator<Item=AuthDetails> {
+        self.ini.iter().filter_map(|(name, section)| {
+            let (username, server) = Self::parse_account_section(name?)?;
+
+            Some(AuthDetails {
+                username: section.get("Username").map_or(username, str::to_owned),
+                password: section.get("Password").unwrap_or("").to_owned(),
+                server: server.to_owned(),
+            })
+        })
+    }
+
+    /// The session key of an account section, or none if it could not be decrypted.
+    fn password(&self, section: &str, password: Option<&str>) -> String {
+        match password {
+            Some(password) if !self.locked.contains(section) => password.to_owned(),
+            _ => String::new(),
+        }
+    }
+
+    /// Returns the username of the last used account on a server.
+    pub fn last_account(&self, server: &str) -> Option<&str> {
+        self.ini.get_from(Some(server), "Account")
+    }
+
+    /// Marks an already stored account as the last used one for its server.
+    pub fn set_last_account(&mut self, server: &str, username: &str) {
+        self.ini.with_section(Some(server)).set("Account", username);
+    }
+
+    /// Decrypts every session key in a config in place. Session keys that can't be decrypted
+    /// (such as when the key file is missing) are left encrypted, so that the rest of the config
+    /// stays usable. Returns whether any session keys were not encrypted, and the sections whose
+    /// session keys could not be decrypted along with why.
+    fn decrypt_passwords(
+        ini: &mut Ini,
+        secrets: &dyn SecretProvider,
+    ) -> (bool, Vec<(String, String)>) {
+        let mut key = None;
+        let mut has_plaintext = false;
+        let mut locked = Vec::new();
+
+        for (name, section) in ini.iter_mut() {
+            let Some(password) = section.get("Password") else { continue };
+
+            if !secrets::is_encrypted(password) {
+                has_plaintext |= !password.is_empty();
+                continue;
+            }
+
+            let key = key.get_or_insert_with(|| match secrets.get_key() {
+                Ok(Some(key)) => Ok(key),
+                Ok(None) => Err("its key file is missing".to_owned()),
+                Err(err) => Err(format!("{err:#}")),
+            });
+
+            let decrypted = key.as_ref().map_err(Clone::clone).and_then(|key| {
+                secrets::decrypt(key, password).map_err(|err| format!("{err:#}"))
+            });
+
+            match decrypted {
+                Ok(decrypted) => section.insert("Password", decrypted),
+                Err(reason) => locked.push((name.unwrap_or_default().to_owned(), reason)),
+            }
+        }
+
+        (has_plaintext, locked)
+    }
+
+    /// Builds the name of the section that stores a single account. Characters of the username
+    /// that would break up the section name (such as the brackets in `[Toy]`) are percent-encoded.
+    pub fn account_section(server: &str, username: &str) -> String {
+        let mut section = String::new();
+        for char in username.chars() {
+            if Self::RESERVED_CHARS.contains(&char) {
+                section.push_str(&*format!("%{0:02X}", char as u32));
+            } else {
+                section.push(char);
+            }
+        }
+
+        format!("{section}@{server}")
+    }
+
+    /// Splits an account section name into its decoded username and server.
+    fn parse_account_section(name: &str) -> Option<(String, &str)> {
+        let (username, server) = name.rsplit_once('@')?;
+        let mut decoded = String::new();
+        let mut rest = username;
+
+        while let Some(idx) = rest.find('%') {
+            decoded.push_str(&rest[..idx]);
+            rest = &rest[idx..];
+
+            match rest.get(1..3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(char) => {
+                    decoded.push(char as char);
+                    rest = &rest[3..];
+                }
+                // Not an encoded character
+                None => {
+                    decoded.push('%');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        decoded.push_str(rest);
+
+        Some((decoded, server))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secrets::KeyFileProvider;
+    use crate::test_util::TempOsuDir;
+
+    fn load_store(osu_dir: &Path) -> SwitcherStore {
+        let secrets = Box::new(KeyFileProvider::new(osu_dir.join("test.key")));
+        SwitcherStore::load_with(osu_dir, secrets).unwrap()
+    }
+
+    fn auth(username: &str, server: &str) -> AuthDetails {
+        AuthDetails {
+            username: username.to_owned(),
+            password: format!("{username}key"),
+            server: server.to_owned(),
+        }
+    }
+
+    #[test]
+    fn round_trips_reserved_usernames() {
+        let osu_dir = TempOsuDir::new("store-reserved");
+        let toy = auth("[Toy]", "akatsuki.gg");
+        let odd = auth("a=b;c#d@e%f\\", "akatsuki.gg");
+
+        let mut store = load_store(&*osu_dir);
+        store.put(&toy);
+        store.put(&odd);
+        store.save().unwrap();
+        store.verify().unwrap();
+
+        let store = load_store(&*osu_dir);
+        assert_eq!(store.get("akatsuki.gg", Some("[Toy]")), Some(toy.clone()));
+        assert_eq!(store.get("akatsuki.gg", None), Some(odd.clone()));
+        assert_eq!(store.accounts(), vec![toy, odd]);
+    }
+
+    #[test]
+    fn decodes_account_sections() {
+        let section = SwitcherStore::account_section("akatsuki.gg", "[Toy]%");
+        assert_eq!(section, "%5BToy%5D%25@akatsuki.gg");
+
+        let (username, server) = SwitcherStore::parse_account_section(&*section).unwrap();
+        assert_eq!((&*username, server), ("[Toy]%", "akatsuki.gg"));
+
+        // Sections written before usernames were encoded are read as is
+        let (username, _) = SwitcherStore::parse_account_section("100%@ripple.moe").unwrap();
+        assert_eq!(username, "100%");
+    }
+
+    #[test]
+    fn forgets_accounts() {
+        let osu_dir = TempOsuDir::new("store-forget");
+        let mut store = load_store(&*osu_dir);
+        store.put(&auth("alice", "akatsuki.gg"));
+        store.put(&auth("bob", "akatsuki.gg"));
+        store.put(&auth("carol", "ripple.moe"));
+
+        // Forgetting the last used account points it to one that is still stored
+        assert_eq!(store.forget("akatsuki.gg", Some("bob")), vec![auth("bob", "akatsuki.gg")]);
+        assert_eq!(store.last_account("akatsuki.gg"), Some("alice"));
+
+        // Forgetting any other account keeps the last used one
+        store.put(&auth("dave", "akatsuki.gg"));
+        store.set_last_account("akatsuki.gg", "alice");
+        store.forget("akatsuki.gg", Some("dave"));
+        assert_eq!(store.last_account("akatsuki.gg"), Some("alice"));
+
+        // Forgetting every account of a server removes its last used account too
+        assert_eq!(store.forget("akatsuki.gg", None), vec![auth("alice", "akatsuki.gg")]);
+        assert_eq!(store.last_account("akatsuki.gg"), None);
+        assert_eq!(store.accounts(), vec![auth("carol", "ripple.moe")]);
+
+        assert!(store.forget("akatsuki.gg", None).is_empty());
+    }
+
+    #[test]
+    fn moves_accounts_between_servers() {
+        let osu_dir = TempOsuDir::new("store-move");
+        let mut store = load_store(&*osu_dir);
+        store.put(&auth("alice", "old.example"));
+        store.put(&auth("bob", "old.example"));
+        store.set_last_account("old.example", "alice");
+
+        assert_eq!(store.move_server("old.example", "new.example").unwrap(), 2);
+        assert_eq!(store.get("old.example", None), None);
+        assert_eq!(store.get("new.example", None), Some(auth("alice", "new.example")));
+        assert_eq!(store.get("new.example", Some("bob")), Some(auth("bob", "new.example")));
+
+        // The new server's last used account is kept
+        store.put(&auth("carol", "other.example"));
+        store.put(&auth("dave", "new.example"));
+        store.set_last_account("new.example", "bob");
+        store.move_server("other.example", "new.example").unwrap();
+        assert_eq!(store.last_account("new.example"), Some("bob"));
+
+        // Accounts that already exist on the new server are never overwritten
+        store.put(&auth("bob", "other.example"));
+        let before = store.accounts();
+        assert!(store.move_server("other.example", "new.example").is_err());
+        assert_eq!(store.accounts(), before);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::store::SwitcherStore;
+use crate::switcher::repair::RepairPolicy;
+use color_eyre::eyre::{bail, Context};
+use color_eyre::Result;
+
+/// A persistent setting of the switcher, stored in the general section of the switcher config.
+pub struct Setting {
+    /// The name of this setting on the command line.
+    pub name: &'static str,
+    /// The key of this setting in the switcher config.
+    key: &'static str,
+    pub description: &'static str,
+    /// The value used when this setting is unset.
+    pub default: &'static str,
+    /// Checks whether a value is valid for this setting.
+    validate: fn(&str) -> Result<()>,
+}
+
+/// What to do when osu! has a pending repair while switching.
+pub const ON_REPAIR: Setting = Setting {
+    name: "on-repair",
+    key: "OnRepair",
+    description: "What to do when osu! has a pending repair (launch, repair or ask)",
+    default: "ask",
+    validate: |value| value.parse::<RepairPolicy>().map(|_| ()),
+};
+
+/// How many seconds to wait for an answer when asking about a pending repair.
+pub const REPAIR_TIMEOUT: Setting = Setting {
+    name: "repair-timeout",
+    key: "RepairTimeout",
+    description: "Seconds to wait for an answer about a pending repair (0 waits forever)",
+    default: "15",
+    validate: |value| value.parse::<u64>().map(|_| ()).context("expected a number of seconds"),
+};
+
+/// What to do when asking about a pending repair times out.
+pub const REPAIR_DEFAULT: Setting = Setting {
+    name: "repair-default",
+    key: "RepairDefault",
+    description: "What to do when asking about a pending repair times out (launch or repair)",
+    default: "launch",
+    validate: |value| match value.parse::<RepairPolicy>()? {
+        RepairPolicy::Ask => bail!("expected launch or repair"),
+        _ => Ok(()),
+    },
+};
+
+/// How many seconds to wait for an osu! repair to finish before giving up on the switch.
+pub const REPAIR_WAIT: Setting = Setting {
+    name: "repair-wait",
+    key: "RepairWait",
+    description: "Seconds to wait for an osu! repair to finish before switching servers",
+    default: "600",
+    validate: |value| value.parse::<u64>().map(|_| ()).context("expected a number of seconds"),
+};
+
+/// Whether to ask osu! to close by itself before killing it.
+pub const CLOSE_GRACEFULLY: Setting = Setting {
+    name: "close-gracefully",
+    key: "CloseGracefully",
+    description: "Ask osu! to close by itself before killing it (true or false)",
+    default: "false",
+    validate: |value| value.parse::<bool>().map(|_| ()).context("expected true or false"),
+};
+
+/// How many seconds to wait for osu! to close by itself before killing it.
+pub const CLOSE_TIMEOUT: Setting = Setting {
+    name: "close-timeout",
+    key: "CloseTimeout",
+    description: "Seconds to wait for osu! to close by itself before killing it",
+    default: "10",
+    validate: |value| value.parse::<u64>().map(|_| ()).context("expected a number of seconds"),
+};
+
+/// Whether to bring osu! to the foreground when it is already running on the target server.
+pub const FOCUS_RUNNING: Setting = Setting {
+    name: "focus-running",
+    key: "FocusRunning",
+    description: "Bring osu! to the foreground if it is already running on the target server",
+    default: "false",
+    validate: |value| value.parse::<bool>().map(|_| ()).context("expected true or false"),
+};
+
+/// Every setting, in the order they are listed.
+pub const ALL: &[&Setting] = &[
+    &ON_REPAIR,
+    &REPAIR_TIMEOUT,
+    &REPAIR_DEFAULT,
+    &REPAIR_WAIT,
+    &CLOSE_GRACEFULLY,
+    &CLOSE_TIMEOUT,
+    &FOCUS_RUNNING,
+];
+
+/// Finds a setting by its name on the command line.
+pub fn find(name: &str) -> Option<&'static Setting> {
+    ALL.iter().copied().find(|setting| setting.name == name)
+}
+
+impl SwitcherStore {
+    /// Returns the value of a setting, or its default if it is unset.
+    pub fn setting(&self, setting: &Setting) -> &str {
+        self.ini
+            .get_from(None::<&str>, setting.key)
+            .unwrap_or(setting.default)
+    }
+
+    /// Returns whether a setting has been explicitly set.
+    pub fn is_setting_set(&self, setting: &Setting) -> bool {
+        self.ini.get_from(None::<&str>, setting.key).is_some()
+    }
+
+    /// Changes the value of a setting, if it is valid for that setting.
+    pub fn set_setting(&mut self, setting: &Setting, value: &str) -> Result<()> {
+        (setting.validate)(value)
+            .with_context(|| format!("invalid value {value:?} for {0}", setting.name))?;
+
+        self.ini.with_general_section().set(setting.key, value);
+        Ok(())
+    }
+}
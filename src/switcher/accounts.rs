@@ -0,0 +1,98 @@
+use crate::servers;
+use crate::store::SwitcherStore;
+use crate::switcher::lock::SwitchLock;
+use crate::switcher::read_current_auth;
+use crate::switcher::status::print_accounts;
+use color_eyre::Result;
+use std::io::Write;
+use std::path::Path;
+
+/// Prints every account stored in the switcher config.
+pub fn list(osu_dir: &Path) -> Result<()> {
+    print_accounts(&SwitcherStore::load(osu_dir)?);
+    Ok(())
+}
+
+/// Removes the stored accounts of a server, or a single one if specified.
+/// Asks for confirmation if the server is the one osu! is currently using, unless `confirmed`.
+pub fn forget(
+    osu_dir: &Path,
+    server: &str,
+    account: Option<&str>,
+    confirmed: bool,
+) -> Result<()> {
+    let server = &*servers::canonicalize(server);
+    let _lock = SwitchLock::acquire(osu_dir)?;
+    let mut store = SwitcherStore::load(osu_dir)?;
+
+    if !confirmed && !confirm_active_server(osu_dir, &[server])? {
+        println!("Cancelled, nothing was changed.");
+        return Ok(());
+    }
+
+    let removed = store.forget(server, account);
+    if removed.is_empty() {
+        println!("No matching sessions are stored for {server}.");
+        return Ok(());
+    }
+
+    store.save()?;
+    store.verify()?;
+
+    for auth in removed {
+        println!("Forgot {0}@{1}", auth.username, auth.server);
+    }
+    Ok(())
+}
+
+/// Moves every stored account from one server to another, such as when a server changes domains.
+/// Asks for confirmation if either server is the one osu! is currently using, unless `confirmed`.
+pub fn move_server(
+    osu_dir: &Path,
+    old_server: &str,
+    new_server: &str,
+    confirmed: bool,
+) -> Result<()> {
+    // The old server is kept as is, since it might be a domain that is not in use anymore
+    let new_server = &*servers::canonicalize(new_server);
+    let _lock = SwitchLock::acquire(osu_dir)?;
+    let mut store = SwitcherStore::load(osu_dir)?;
+
+    if !confirmed && !confirm_active_server(osu_dir, &[old_server, new_server])? {
+        println!("Cancelled, nothing was changed.");
+        return Ok(());
+    }
+
+    let moved = store.move_server(old_server, new_server)?;
+    if moved == 0 {
+        println!("No sessions are stored for {old_server}.");
+        return Ok(());
+    }
+
+    store.save()?;
+    store.verify()?;
+
+    println!("Moved {moved} stored session(s) from {old_server} to {new_server}");
+    Ok(())
+}
+
+/// If any of the servers is the one osu! is currently signed in to, then asks the user to
+/// confirm changing its stored sessions. Returns whether to continue.
+fn confirm_active_server(osu_dir: &Path, servers: &[&str]) -> Result<bool> {
+    let Some(current) = read_current_auth(osu_dir)? else { return Ok(true) };
+
+    if !servers.contains(&&*current.server) {
+        return Ok(true);
+    }
+
+    print!(
+        "osu! is currently signed in to {0}. Change its stored sessions anyway? [y/N] ",
+        current.server
+    );
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
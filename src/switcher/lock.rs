@@ -0,0 +1,141 @@
+use color_eyre::eyre::{bail, Context};
+use color_eyre::Result;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{fs, process, thread};
+
+/// An advisory lock file inside the osu! directory that prevents multiple switches from running
+/// at the same time, which would race on osu!'s configs. The file is locked through the OS, so
+/// the lock is released when dropped, and also when its owner crashes. The file itself stays.
+pub struct SwitchLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl SwitchLock {
+    /// The file name of the lock file inside the osu! directory.
+    pub const FILE_NAME: &'static str = "osu!switcher.lock";
+
+    /// How long to wait for another switch to finish before refusing to switch.
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Acquires the lock for an osu! directory, waiting in line if another switch holds it.
+    pub fn acquire(osu_dir: &Path) -> Result<Self> {
+        let path = osu_dir.join(Self::FILE_NAME);
+        let started = Instant::now();
+        let mut waiting = false;
+
+        loop {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&*path)
+                .with_context(|| format!("failed to open lock {path:?}"))?;
+
+            match file.try_lock() {
+                // Locking a lock file that was deleted in the meantime wouldn't exclude anyone
+                Ok(()) if !Self::is_current(&file, &*path) => continue,
+                Ok(()) => {
+                    // The PID is only informational, for other switches waiting on this one
+                    file.set_len(0).context("failed to write switch lock")?;
+                    write!(file, "{}", process::id()).context("failed to write switch lock")?;
+                    return Ok(Self { file, path });
+                }
+                Err(TryLockError::WouldBlock) => {}
+                Err(TryLockError::Error(err)) => {
+                    return Err(err).with_context(|| format!("failed to lock {path:?}"));
+                }
+            }
+
+            // Windows doesn't allow reading a locked file
+            let owner = fs::read_to_string(&*path)
+                .ok()
+                .and_then(|contents| contents.trim().parse::<u32>().ok())
+                .map_or("unknown PID".to_owned(), |pid| format!("PID {pid}"));
+
+            if started.elapsed() >= Self::TIMEOUT {
+                bail!(
+                    "another switch ({owner}) is still running after {0}s, refusing to \
+                    switch at the same time",
+                    Self::TIMEOUT.as_secs()
+                );
+            }
+            if !waiting {
+                println!("Another switch ({owner}) is already running, waiting for it to finish");
+                waiting = true;
+            }
+
+            thread::sleep(Duration::from_millis(250));
+        }
+    }
+
+    /// Deletes the lock file and releases the lock, for osu! directories that the switcher
+    /// leaves behind. Switches waiting on this lock notice that it was deleted, and start over.
+    pub fn remove(self) -> Result<()> {
+        fs::remove_file(&*self.path)
+            .with_context(|| format!("failed to delete lock {0:?}", self.path))
+    }
+
+    /// Checks whether an open lock file is still the one at its path, and wasn't deleted.
+    #[cfg(unix)]
+    fn is_current(file: &File, path: &Path) -> bool {
+        use std::os::unix::fs::MetadataExt;
+
+        match (file.metadata(), fs::metadata(path)) {
+            (Ok(opened), Ok(current)) => {
+                opened.dev() == current.dev() && opened.ino() == current.ino()
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks whether an open lock file is still the one at its path, and wasn't deleted.
+    /// File identities can't be compared here, so the lock file only has to exist.
+    #[cfg(not(unix))]
+    fn is_current(_file: &File, path: &Path) -> bool {
+        fs::exists(path).unwrap_or(false)
+    }
+}
+
+impl Drop for SwitchLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempOsuDir;
+
+    #[test]
+    fn excludes_other_switches() {
+        let osu_dir = TempOsuDir::new("lock");
+        let path = osu_dir.join(SwitchLock::FILE_NAME);
+
+        let lock = SwitchLock::acquire(&*osu_dir).unwrap();
+        let other = File::open(&*path).unwrap();
+        assert!(matches!(other.try_lock(), Err(TryLockError::WouldBlock)));
+        assert_eq!(fs::read_to_string(&*path).unwrap(), process::id().to_string());
+
+        // A deleted lock file can't be used anymore, even by those that opened it before
+        fs::remove_file(&*path).unwrap();
+        drop(lock);
+        assert!(!SwitchLock::is_current(&other, &*path));
+
+        drop(SwitchLock::acquire(&*osu_dir).unwrap());
+    }
+
+    #[test]
+    fn removes_lock_file() {
+        let osu_dir = TempOsuDir::new("lock-remove");
+        let path = osu_dir.join(SwitchLock::FILE_NAME);
+
+        SwitchLock::acquire(&*osu_dir).unwrap().remove().unwrap();
+        assert!(!fs::exists(&*path).unwrap());
+    }
+}
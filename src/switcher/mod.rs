@@ -0,0 +1,648 @@
+use crate::history;
+use crate::history::{HistoryEntry, Outcome};
+use crate::launcher::Launcher;
+use crate::process;
+use crate::process::ProcessController;
+use crate::servers;
+use crate::store::settings::{
+    CLOSE_GRACEFULLY, CLOSE_TIMEOUT, FOCUS_RUNNING, ON_REPAIR, REPAIR_DEFAULT, REPAIR_TIMEOUT,
+    REPAIR_WAIT,
+};
+use crate::store::{AuthDetails, SwitcherStore};
+use color_eyre::eyre::{Context, ContextCompat};
+use color_eyre::{Report, Result};
+use ini::Ini;
+use lock::SwitchLock;
+use plan::{Step, SwitchPlan, ValueChange};
+use repair::RepairPolicy;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub mod accounts;
+pub mod lock;
+pub mod plan;
+pub mod repair;
+pub mod settings;
+pub mod status;
+mod transaction;
+
+/// Options for a single switch between servers.
+#[derive(Debug, Default)]
+pub struct SwitchOptions {
+    /// The domain of the server to switch to.
+    pub server: String,
+    /// The stored account on the target server to switch to, or the last used one if unset.
+    pub account: Option<String>,
+    /// Prints the plan of what would be changed instead of changing anything.
+    pub dry_run: bool,
+    /// What to do if osu! has a pending repair, or the `on-repair` setting if unset.
+    pub on_repair: Option<RepairPolicy>,
+    /// Stops every running osu!, instead of only the one running from the target installation.
+    pub kill_all: bool,
+    /// How to launch osu! once switched.
+    pub launcher: Launcher,
+    /// The system user whose osu! config (`osu!.<user>.cfg`) gets switched, or the current one
+    /// if unset.
+    pub system_user: Option<String>,
+}
+
+/// Switches osu!'s configuration to replace the authentication details with ones for a different
+/// server (or a different account on it), if they exist. Afterward, this relaunches osu!.
+/// If osu! is allowed to repair itself first, then the switch is completed once it has finished.
+pub fn switch_servers(osu_dir: &str, options: &SwitchOptions) -> Result<()> {
+    let options = &SwitchOptions {
+        server: servers::canonicalize(&*options.server),
+        account: options.account.clone(),
+        launcher: options.launcher.clone(),
+        system_user: options.system_user.clone(),
+        ..*options
+    };
+    let target_server = &*options.server;
+
+    println!("Using '{osu_dir}' as the target osu! installation!");
+    match &options.account {
+        Some(account) => println!("Switching to '{account}' on '{target_server}'"),
+        None => println!("Switching to '{target_server}'"),
+    }
+
+    if options.dry_run {
+        if skip_running(Path::new(osu_dir), options, &mut HistoryEntry::new())? {
+            return Ok(());
+        }
+
+        let plan = plan_switch(Path::new(osu_dir), options, &mut HistoryEntry::new())?;
+
+        println!("\nDry run, nothing will be changed. The switch would perform these steps:");
+        print!("{plan}");
+        return Ok(());
+    }
+
+    // Switches that end before they get to run are recorded in the history all the same
+    let mut entry = HistoryEntry::new();
+
+    // Held until the switch finishes, so that concurrent switches don't race on the configs
+    let _lock = match SwitchLock::acquire(Path::new(osu_dir)) {
+        Ok(lock) => lock,
+        Err(err) => return Err(record_failure(Path::new(osu_dir), entry, err)),
+    };
+
+    match skip_running(Path::new(osu_dir), options, &mut entry) {
+        Ok(false) => {}
+        Ok(true) => {
+            let reason = format!("osu! is already running on '{target_server}'");
+            entry.outcome = Outcome::Skipped(reason);
+            record(Path::new(osu_dir), &entry);
+            return Ok(());
+        }
+        Err(err) => return Err(record_failure(Path::new(osu_dir), entry, err)),
+    }
+
+    if run_switch(Path::new(osu_dir), options)? {
+        let waited = SwitcherStore::load(Path::new(osu_dir))
+            .and_then(|store| {
+                store.setting(&REPAIR_WAIT).parse().context("invalid repair-wait setting")
+            })
+            .and_then(|timeout| repair::wait(Path::new(osu_dir), Duration::from_secs(timeout)));
+
+        if let Err(err) = waited {
+            return Err(record_failure(Path::new(osu_dir), HistoryEntry::new(), err));
+        }
+        println!("osu! has finished repairing, completing the switch to '{target_server}'");
+
+        run_switch(Path::new(osu_dir), options)?;
+    }
+
+    Ok(())
+}
+
+/// Appends an entry to the history, only warning if that fails so that the switch isn't affected.
+fn record(osu_dir: &Path, entry: &HistoryEntry) {
+    if let Err(err) = history::append(osu_dir, entry) {
+        println!("Failed to record this switch in the history: {err:#}");
+    }
+}
+
+/// Records a switch that failed in the history, and returns its error.
+fn record_failure(osu_dir: &Path, mut entry: HistoryEntry, err: Report) -> Report {
+    entry.outcome = Outcome::Failed(format!("{err:#}"));
+    record(osu_dir, &entry);
+    err
+}
+
+/// Checks whether osu! is already running on the target server, in which case there is nothing
+/// to switch and restarting it is skipped. If enabled, it is brought to the foreground instead.
+/// The running account is recorded to the history entry.
+fn skip_running(
+    osu_dir: &Path,
+    options: &SwitchOptions,
+    entry: &mut HistoryEntry,
+) -> Result<bool> {
+    let controller = process::default_controller();
+    let Some((pid, auth)) = find_running_on_target(osu_dir, options, &*controller)? else {
+        return Ok(false);
+    };
+
+    println!("osu! is already running on '{0}' (PID {pid}), skipping the switch", options.server);
+    entry.from = Some(auth.clone());
+    entry.to = Some(auth);
+
+    let focus: bool = SwitcherStore::load(osu_dir)?
+        .setting(&FOCUS_RUNNING)
+        .parse()
+        .context("invalid focus-running setting")?;
+
+    if focus && !options.dry_run {
+        // osu! only runs once, so this hands over to the running osu! and brings it to the front
+        options.launcher.launch(&*osu_dir.join("osu!.exe"), &*options.server)?;
+        println!("Brought osu! to the foreground");
+    }
+
+    Ok(true)
+}
+
+/// Finds an osu! process running from the osu! installation that was launched for the target
+/// server, while osu!'s config is also signed in to the target server and account.
+/// Returns its PID along with the account it is signed in to.
+fn find_running_on_target(
+    osu_dir: &Path,
+    options: &SwitchOptions,
+    controller: &dyn ProcessController,
+) -> Result<Option<(u32, AuthDetails)>> {
+    let endpoint = servers::endpoint(&*options.server);
+    let system_user = options.system_user.as_deref();
+    let Some(osu_ini) = read_osu_config(osu_dir, system_user)? else { return Ok(None) };
+    let auth = extract_auth_details(&osu_ini)?;
+
+    // An old domain of the target server still has to be switched away from
+    if osu_ini.get_from(None::<String>, "CredentialEndpoint").unwrap_or("") != endpoint {
+        return Ok(None);
+    }
+    if options.account.as_ref().is_some_and(|account| *account != auth.username) {
+        return Ok(None);
+    }
+    // Such as after a repair, which signs out of osu!
+    if auth.username.is_empty()
+        && SwitcherStore::load(osu_dir)?
+            .get(&*options.server, options.account.as_deref())
+            .is_some()
+    {
+        return Ok(None);
+    }
+
+    let running = controller
+        .find_osu(Some(osu_dir))?
+        .into_iter()
+        // Without a known executable, it might be running from a different installation
+        .find(|process| process.exe.is_some() && process.devserver() == endpoint);
+
+    Ok(running.map(|process| (process.pid, auth)))
+}
+
+/// Stops osu!, then plans and applies a switch, recording it in the history.
+/// Returns whether an osu! repair was launched instead, after which the switch has to be redone.
+fn run_switch(osu_dir: &Path, options: &SwitchOptions) -> Result<bool> {
+    let mut entry = HistoryEntry::new();
+
+    // osu! writes its config when closing, so it has to be stopped before reading the configs.
+    // Otherwise, the switch would be planned from outdated configs
+    let result = SwitcherStore::load(osu_dir)
+        .and_then(|store| plan_stop(osu_dir, options, &store))
+        .and_then(|stop| stop.apply())
+        .and_then(|_| plan_switch(osu_dir, options, &mut entry))
+        .and_then(|plan| plan.apply().map(|_| plan.awaits_repair()));
+
+    match result {
+        Ok(awaits_repair) => {
+            record(osu_dir, &entry);
+            Ok(awaits_repair)
+        }
+        Err(err) => Err(record_failure(osu_dir, entry, err)),
+    }
+}
+
+/// Reads the current state of the osu! installation and plans every change needed to switch
+/// to the target server, without changing anything yet. The accounts involved are recorded to
+/// the history entry as soon as they are known.
+fn plan_switch(
+    osu_dir: &Path,
+    options: &SwitchOptions,
+    entry: &mut HistoryEntry,
+) -> Result<SwitchPlan> {
+    let target_server = &*options.server;
+    let target_account = options.account.as_deref();
+
+    let osu_cfg = osu_config_path(osu_dir, options.system_user.as_deref())?;
+    let osu_exe = osu_dir.join("osu!.exe");
+    let osu_db = osu_dir.join("osu!.db");
+
+    let mut store = SwitcherStore::load(osu_dir)?;
+    let mut plan = SwitchPlan::default();
+    let launch = Step::Launch {
+        launcher: options.launcher.clone(),
+        osu_exe,
+        server: target_server.to_owned(),
+    };
+
+    // Stop osu! before touching any files, in case it was started again since it was last stopped
+    plan.push(plan_stop(osu_dir, options, &store)?);
+
+    // Ensure main auth config exists
+    if !fs::exists(&*osu_cfg)? {
+        let cfg_name = osu_cfg.file_name().unwrap_or_default().to_string_lossy();
+        println!("Missing {cfg_name}, launching the game normally...");
+        plan_clear_logs(osu_dir, &mut plan)?;
+        plan.push(launch);
+        return Ok(plan);
+    }
+
+    // Load configs
+    let old_osu_ini = Ini::load_from_file(&osu_cfg)
+        .with_context(|| format!("failed to read osu! config {osu_cfg:?}"))?;
+    let mut osu_ini = old_osu_ini.clone();
+
+    // Extract old auth info from osu config
+    let old_auth = extract_auth_details(&osu_ini)?;
+    entry.from = Some(old_auth.clone());
+
+    plan_clear_logs(osu_dir, &mut plan)?;
+
+    // If pending update confirmed, then save and remove all auth and launch directly.
+    // The switch is completed once the repair has finished
+    if !plan_clear_updater(osu_dir, options, &store, &mut plan)? {
+        osu_ini
+            .with_section(None::<String>)
+            .set("Username", "")
+            .set("Password", "")
+            .set("CredentialEndpoint", "");
+
+        entry.to = Some(AuthDetails {
+            username: String::new(),
+            password: String::new(),
+            server: target_server.to_owned(),
+        });
+        plan.push(Step::WriteConfig {
+            changes: ValueChange::diff_ini(&old_osu_ini, &osu_ini),
+            path: osu_cfg,
+            ini: osu_ini,
+        });
+
+        store.put(&old_auth);
+        plan.push(Step::WriteStore { store });
+        plan.push(launch);
+        plan.await_repair();
+        return Ok(plan);
+    }
+
+    let switching_account = target_account.is_some_and(|account| account != old_auth.username);
+    // Such as after a repair, which signs out of osu!
+    let restoring_session =
+        old_auth.username.is_empty() && store.get(target_server, target_account).is_some();
+    entry.to = Some(old_auth.clone());
+
+    if old_auth.server != target_server || switching_account || restoring_session {
+        let new_auth = store
+            .get(target_server, target_account)
+            .unwrap_or_else(|| AuthDetails {
+                // Prefill the requested account so that only the password has to be entered
+                username: target_account.unwrap_or("").to_owned(),
+                password: String::from(""),
+                server: target_server.to_owned(),
+            });
+        entry.to = Some(new_auth.clone());
+
+        let db = osu_db::Listing::from_file(&*osu_db).context("failed to open osu!.db")?;
+        plan.push(Step::EditDb {
+            path: osu_db,
+            old: db.player_name,
+            new: new_auth.username.clone(),
+        });
+
+        osu_ini
+            .with_section(None::<String>)
+            .set("Username", new_auth.username)
+            .set("Password", new_auth.password)
+            .set("CredentialEndpoint", servers::endpoint(target_server));
+        plan.push(Step::WriteConfig {
+            changes: ValueChange::diff_ini(&old_osu_ini, &osu_ini),
+            path: osu_cfg,
+            ini: osu_ini,
+        });
+    } else if old_osu_ini.get_from(None::<String>, "CredentialEndpoint").unwrap_or("")
+        != servers::endpoint(target_server)
+    {
+        // Signed in to an old domain of the same server
+        osu_ini
+            .with_section(None::<String>)
+            .set("CredentialEndpoint", servers::endpoint(target_server));
+        plan.push(Step::WriteConfig {
+            changes: ValueChange::diff_ini(&old_osu_ini, &osu_ini),
+            path: osu_cfg,
+            ini: osu_ini,
+        });
+    }
+
+    // *Always* save old credentials to switcher config
+    store.put(&old_auth);
+    plan.push(Step::WriteStore { store });
+
+    plan.push(launch);
+    Ok(plan)
+}
+
+/// Plans stopping osu! according to the close settings.
+fn plan_stop(osu_dir: &Path, options: &SwitchOptions, store: &SwitcherStore) -> Result<Step> {
+    let graceful: bool = store
+        .setting(&CLOSE_GRACEFULLY)
+        .parse()
+        .context("invalid close-gracefully setting")?;
+    let close_timeout: u64 = store
+        .setting(&CLOSE_TIMEOUT)
+        .parse()
+        .context("invalid close-timeout setting")?;
+
+    Ok(Step::Stop {
+        osu_dir: (!options.kill_all).then(|| osu_dir.to_owned()),
+        close_timeout: graceful.then(|| Duration::from_secs(close_timeout)),
+    })
+}
+
+/// Reads the authentication details osu! is currently configured with,
+/// or `None` if osu! has not created its main config yet.
+pub fn read_current_auth(osu_dir: &Path) -> Result<Option<AuthDetails>> {
+    match read_osu_config(osu_dir, None)? {
+        Some(osu_ini) => extract_auth_details(&osu_ini).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Returns the path to osu!'s main config of a system user, or of the current one if unset.
+fn osu_config_path(osu_dir: &Path, system_user: Option<&str>) -> Result<PathBuf> {
+    let system_user = match system_user {
+        Some(system_user) => system_user.to_owned(),
+        None => whoami::username().context("failed getting system username")?,
+    };
+
+    Ok(osu_dir.join(format!("osu!.{system_user}.cfg")))
+}
+
+/// Reads osu!'s main config of a system user (or of the current one if unset),
+/// or `None` if osu! has not created it yet.
+fn read_osu_config(osu_dir: &Path, system_user: Option<&str>) -> Result<Option<Ini>> {
+    let osu_cfg = osu_config_path(osu_dir, system_user)?;
+
+    if !fs::exists(&*osu_cfg)? {
+        return Ok(None);
+    }
+
+    let osu_ini = Ini::load_from_file(&*osu_cfg)
+        .with_context(|| format!("failed to read osu! config {osu_cfg:?}"))?;
+    Ok(Some(osu_ini))
+}
+
+/// Extracts authentication details from osu!'s main config.
+fn extract_auth_details(osu_config: &Ini) -> Result<AuthDetails> {
+    let cfg = osu_config
+        .section(None::<String>)
+        .context("corrupted osu! config")?;
+
+    Ok(AuthDetails {
+        server: servers::from_endpoint(cfg.get("CredentialEndpoint").unwrap_or("")),
+        username: cfg.get("Username").unwrap_or("").to_owned(),
+        password: cfg.get("Password").unwrap_or("").to_owned(),
+    })
+}
+
+/// Plans clearing the osu!auth logs.
+fn plan_clear_logs(osu_dir: &Path, plan: &mut SwitchPlan) -> Result<()> {
+    let auth_path = osu_dir.join("Logs/osu!auth.log");
+
+    if fs::exists(&*auth_path)? {
+        // I have no clue what this contains, but I have heard about this potentially containing anti-multi-accounting
+        // data, which might interfere with switching accounts across servers. Just to be safe, wipe it regardless.
+        plan.push(Step::DeleteFile {
+            path: auth_path,
+            reason: "clears osu!auth logs",
+        });
+    }
+
+    Ok(())
+}
+
+/// Decides what to do with osu!'s force updater flag if present, asking the user if the
+/// repair policy says so. If true returned, then continue switching to a server, otherwise,
+/// remove all credentials to allow updater to do its job so that osu! can be safely restarted
+/// afterward.
+fn plan_clear_updater(
+    osu_dir: &Path,
+    options: &SwitchOptions,
+    store: &SwitcherStore,
+    plan: &mut SwitchPlan,
+) -> Result<bool> {
+    // If this is present, it causes osu! to relaunch and repair itself,
+    // which doesn't preserve the -devserver argument
+    let force_update_file = osu_dir.join(".require_update");
+
+    if !fs::exists(&*force_update_file)? {
+        return Ok(true);
+    }
+
+    let policy = match options.on_repair {
+        Some(policy) => policy,
+        None => store.setting(&ON_REPAIR).parse()?,
+    };
+
+    let launch = match policy {
+        RepairPolicy::Launch => true,
+        RepairPolicy::Repair => false,
+        RepairPolicy::Ask if options.dry_run => {
+            plan.note(
+                "a pending osu! repair was detected, which prompts whether to continue \
+                [L]aunching (planned below) or allow [R]epair (removes all credentials and \
+                launches the updater)",
+            );
+            true
+        }
+        RepairPolicy::Ask => {
+            let timeout: u64 = store
+                .setting(&REPAIR_TIMEOUT)
+                .parse()
+                .context("invalid repair-timeout setting")?;
+            let default: RepairPolicy = store.setting(&REPAIR_DEFAULT).parse()?;
+
+            let timeout = (timeout > 0).then(|| Duration::from_secs(timeout));
+            repair::prompt(timeout, default == RepairPolicy::Launch)?
+        }
+    };
+
+    if !options.dry_run && policy != RepairPolicy::Ask {
+        println!("Detected a pending osu! repair, handling it with the '{policy}' policy");
+    }
+
+    if launch {
+        plan.push(Step::DeleteFile {
+            path: force_update_file,
+            reason: "cancels the pending osu! repair",
+        });
+    }
+
+    Ok(launch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeController;
+    use crate::test_util::TempOsuDir;
+
+    /// Sets up an osu! directory signed in to an account, with `bob` stored for akatsuki.gg.
+    fn setup_osu_dir(name: &str, username: &str, endpoint: &str) -> TempOsuDir {
+        let osu_dir = TempOsuDir::new(name);
+        let config = format!("Username = {username}\nCredentialEndpoint = {endpoint}\n");
+        fs::write(osu_dir.join("osu!.tester.cfg"), config).unwrap();
+        fs::write(
+            osu_dir.join(SwitcherStore::FILE_NAME),
+            "Version=2\n\
+            [bob@akatsuki.gg]\nUsername=bob\nPassword=bobkey\n\
+            [akatsuki.gg]\nAccount=bob\n",
+        )
+        .unwrap();
+        osu_db::Listing {
+            version: 20250107,
+            folder_count: 0,
+            unban_date: None,
+            player_name: Some(username.to_owned()),
+            beatmaps: Vec::new(),
+            user_permissions: 0,
+        }
+        .save(&*osu_dir.join("osu!.db"))
+        .unwrap();
+
+        osu_dir
+    }
+
+    fn switch_to(server: &str, account: Option<&str>) -> SwitchOptions {
+        SwitchOptions {
+            server: server.to_owned(),
+            account: account.map(str::to_owned),
+            system_user: Some("tester".to_owned()),
+            ..SwitchOptions::default()
+        }
+    }
+
+    /// Returns the osu! config that a plan writes, if any.
+    fn written_config(plan: &SwitchPlan) -> Option<&Ini> {
+        plan.steps().iter().find_map(|step| match step {
+            Step::WriteConfig { ini, .. } => Some(ini),
+            _ => None,
+        })
+    }
+
+    /// Returns the player name that a plan writes into `osu!.db`, if any.
+    fn written_player_name(plan: &SwitchPlan) -> Option<&str> {
+        plan.steps().iter().find_map(|step| match step {
+            Step::EditDb { new, .. } => Some(&**new),
+            _ => None,
+        })
+    }
+
+    fn written_store(plan: &SwitchPlan) -> Option<&SwitcherStore> {
+        plan.steps().iter().find_map(|step| match step {
+            Step::WriteStore { store } => Some(store),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn switches_server() {
+        let osu_dir = setup_osu_dir("switch-server", "alice", "ripple.moe");
+        let mut entry = HistoryEntry::new();
+        let plan = plan_switch(&*osu_dir, &switch_to("akatsuki.gg", None), &mut entry).unwrap();
+
+        let config = written_config(&plan).unwrap();
+        assert_eq!(config.get_from(None::<&str>, "Username"), Some("bob"));
+        assert_eq!(config.get_from(None::<&str>, "Password"), Some("bobkey"));
+        assert_eq!(config.get_from(None::<&str>, "CredentialEndpoint"), Some("akatsuki.gg"));
+        assert_eq!(written_player_name(&plan), Some("bob"));
+
+        let store = written_store(&plan).unwrap();
+        assert_eq!(store.get("ripple.moe", None).unwrap().username, "alice");
+        assert_eq!(entry.from.unwrap().server, "ripple.moe");
+        assert_eq!(entry.to.unwrap().username, "bob");
+    }
+
+    #[test]
+    fn switches_account_on_same_server() {
+        let osu_dir = setup_osu_dir("switch-account", "alice", "akatsuki.gg");
+        let mut entry = HistoryEntry::new();
+        let options = switch_to("akatsuki.gg", Some("bob"));
+        let plan = plan_switch(&*osu_dir, &options, &mut entry).unwrap();
+
+        let config = written_config(&plan).unwrap();
+        assert_eq!(config.get_from(None::<&str>, "Username"), Some("bob"));
+        assert_eq!(written_player_name(&plan), Some("bob"));
+        assert_eq!(written_store(&plan).unwrap().last_account("akatsuki.gg"), Some("alice"));
+
+        // Without a requested account, the signed in one is kept
+        let mut entry = HistoryEntry::new();
+        let plan = plan_switch(&*osu_dir, &switch_to("akatsuki.gg", None), &mut entry).unwrap();
+        assert!(written_config(&plan).is_none());
+        assert!(written_player_name(&plan).is_none());
+        assert_eq!(entry.to.unwrap().username, "alice");
+    }
+
+    #[test]
+    fn moves_old_domain_of_same_server() {
+        let osu_dir = setup_osu_dir("switch-old-domain", "alice", "akatsuki.pw");
+        let options = switch_to("akatsuki.gg", None);
+        let osu_exe = osu_dir.join("osu!.exe");
+        let controller = FakeController::default()
+            .with_process_at(100, &*osu_exe.to_string_lossy())
+            .started_with(100, &["-devserver", "akatsuki.pw"]);
+
+        // osu! still has to be restarted on the new domain
+        let running = find_running_on_target(&*osu_dir, &options, &controller).unwrap();
+        assert!(running.is_none());
+
+        let mut entry = HistoryEntry::new();
+        let plan = plan_switch(&*osu_dir, &options, &mut entry).unwrap();
+        let config = written_config(&plan).unwrap();
+        assert_eq!(config.get_from(None::<&str>, "Username"), Some("alice"));
+        assert_eq!(config.get_from(None::<&str>, "CredentialEndpoint"), Some("akatsuki.gg"));
+        assert!(written_player_name(&plan).is_none());
+        assert_eq!(entry.from.unwrap().server, "akatsuki.gg");
+    }
+
+    #[test]
+    fn finds_osu_running_on_target() {
+        let osu_dir = setup_osu_dir("switch-running", "alice", "akatsuki.gg");
+        let osu_exe = osu_dir.join("osu!.exe");
+        let controller = FakeController::default()
+            .with_process_at(100, &*osu_exe.to_string_lossy())
+            .started_with(100, &["-devserver", "akatsuki.gg"]);
+
+        let options = switch_to("akatsuki.gg", None);
+        let (pid, auth) = find_running_on_target(&*osu_dir, &options, &controller)
+            .unwrap()
+            .unwrap();
+        assert_eq!((pid, &*auth.username), (100, "alice"));
+
+        let options = switch_to("akatsuki.gg", Some("bob"));
+        assert!(find_running_on_target(&*osu_dir, &options, &controller).unwrap().is_none());
+    }
+
+    #[test]
+    fn launches_without_config() {
+        let osu_dir = setup_osu_dir("switch-missing-config", "alice", "akatsuki.gg");
+        fs::remove_file(osu_dir.join("osu!.tester.cfg")).unwrap();
+        let options = switch_to("akatsuki.gg", None);
+
+        let controller = FakeController::default();
+        assert!(find_running_on_target(&*osu_dir, &options, &controller).unwrap().is_none());
+
+        let mut entry = HistoryEntry::new();
+        let plan = plan_switch(&*osu_dir, &options, &mut entry).unwrap();
+        assert!(matches!(plan.steps(), [Step::Stop { .. }, Step::Launch { .. }]));
+        assert!(entry.from.is_none());
+    }
+}
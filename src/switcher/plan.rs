@@ -0,0 +1,352 @@
+use crate::launcher::Launcher;
+use crate::process;
+use crate::secrets;
+use crate::store::migrations::Migration;
+use crate::store::SwitcherStore;
+use crate::switcher::transaction::Transaction;
+use color_eyre::eyre::{bail, Context};
+use color_eyre::Result;
+use ini::Ini;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A single value that is changed inside a config file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ValueChange {
+    /// The name of the value, prefixed by its section if it has one. ex: `[localhost] Account`
+    pub key: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl ValueChange {
+    /// Compares two versions of an ini config and returns every value that differs.
+    pub fn diff_ini(old: &Ini, new: &Ini) -> Vec<ValueChange> {
+        let mut changes = Vec::new();
+
+        for (section, props) in new.iter() {
+            for (key, value) in props.iter() {
+                let old_value = old.get_from(section, key);
+
+                if old_value != Some(value) {
+                    changes.push(ValueChange {
+                        key: Self::qualified_key(section, key),
+                        old: old_value.map(str::to_owned),
+                        new: Some(value.to_owned()),
+                    });
+                }
+            }
+        }
+
+        for (section, props) in old.iter() {
+            for (key, value) in props.iter() {
+                if new.get_from(section, key).is_none() {
+                    changes.push(ValueChange {
+                        key: Self::qualified_key(section, key),
+                        old: Some(value.to_owned()),
+                        new: None,
+                    });
+                }
+            }
+        }
+
+        changes
+    }
+
+    fn qualified_key(section: Option<&str>, key: &str) -> String {
+        match section {
+            Some(section) => format!("[{section}] {key}"),
+            None => key.to_owned(),
+        }
+    }
+
+    /// Whether this value holds a session key, which should never be printed.
+    fn is_secret(&self) -> bool {
+        self.key.ends_with("Password")
+    }
+
+    fn fmt_value(&self, value: &Option<String>) -> String {
+        match value {
+            None => "<unset>".to_owned(),
+            Some(value) if value.is_empty() => "\"\"".to_owned(),
+            Some(value) if self.is_secret() => secrets::mask(value).to_owned(),
+            Some(value) => format!("{value:?}"),
+        }
+    }
+}
+
+impl Display for ValueChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let old = self.fmt_value(&self.old);
+        let new = self.fmt_value(&self.new);
+
+        // Differing session keys would otherwise look identical when masked
+        if self.is_secret() && old == new {
+            write!(f, "{0}: {old} -> <different session key>", self.key)
+        } else {
+            write!(f, "{0}: {old} -> {new}", self.key)
+        }
+    }
+}
+
+/// A single step of switching servers that touches the disk or the running game.
+// Plans only hold a handful of steps, so boxing the configs isn't worth it
+#[allow(clippy::large_enum_variant)]
+pub enum Step {
+    /// Deletes a file from the osu! directory.
+    DeleteFile { path: PathBuf, reason: &'static str },
+    /// Replaces the player name stored in `osu!.db`.
+    EditDb {
+        path: PathBuf,
+        old: Option<String>,
+        new: String,
+    },
+    /// Writes the edited osu! config back to disk.
+    WriteConfig {
+        path: PathBuf,
+        ini: Ini,
+        changes: Vec<ValueChange>,
+    },
+    /// Writes the edited switcher config back to disk.
+    WriteStore { store: SwitcherStore },
+    /// Stops any osu! running from an installation (or any osu! at all if unset), so that it
+    /// can't overwrite the edited files when it exits. If a close timeout is set, then osu! is
+    /// asked to close by itself before being killed.
+    Stop {
+        osu_dir: Option<PathBuf>,
+        close_timeout: Option<Duration>,
+    },
+    /// Launches osu! with a specified server.
+    Launch {
+        launcher: Launcher,
+        osu_exe: PathBuf,
+        server: String,
+    },
+}
+
+impl Step {
+    /// Performs this step.
+    pub fn apply(&self) -> Result<()> {
+        match self {
+            Step::DeleteFile { path, .. } => {
+                fs::remove_file(path).with_context(|| format!("failed to delete {path:?}"))
+            }
+            Step::EditDb { path, new, .. } => edit_db(path, new),
+            Step::WriteConfig { path, ini, .. } => ini
+                .write_to_file(path)
+                .context("failed to write osu! config"),
+            Step::WriteStore { store } => store.save(),
+            Step::Stop {
+                osu_dir,
+                close_timeout,
+            } => {
+                let controller = process::default_controller();
+                let stopped = process::stop_osu(&*controller, osu_dir.as_deref(), *close_timeout)
+                    .context("failed to stop osu!")?;
+
+                if stopped > 0 {
+                    println!("Stopped running osu!.exe");
+                }
+                Ok(())
+            }
+            Step::Launch {
+                launcher,
+                osu_exe,
+                server,
+            } => launcher.launch(osu_exe, server),
+        }
+    }
+
+    /// Re-reads the files written by this step to ensure it was applied correctly.
+    pub fn verify(&self) -> Result<()> {
+        match self {
+            Step::DeleteFile { path, .. } => {
+                if fs::exists(path).unwrap_or(true) {
+                    bail!("{path:?} still exists after deleting it");
+                }
+            }
+            Step::EditDb { path, new, .. } => {
+                let db = osu_db::Listing::from_file(path).context("failed to re-read osu!.db")?;
+                if db.player_name.as_deref() != Some(&**new) {
+                    bail!("osu!.db player name was not updated");
+                }
+            }
+            Step::WriteConfig { path, ini, .. } => {
+                let written = Ini::load_from_file(path).context("failed to re-read osu! config")?;
+                if !ValueChange::diff_ini(ini, &written).is_empty() {
+                    bail!("osu! config {path:?} does not match what was written");
+                }
+            }
+            Step::WriteStore { store } => store.verify()?,
+            Step::Stop { .. } | Step::Launch { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// The files that this step writes to or deletes.
+    pub fn paths(&self) -> Vec<&Path> {
+        match self {
+            Step::DeleteFile { path, .. }
+            | Step::EditDb { path, .. }
+            | Step::WriteConfig { path, .. } => vec![&**path],
+            Step::WriteStore { store } => [
+                Some(store.path()),
+                store.legacy_path(),
+                store.backup_path(),
+            ]
+                .into_iter()
+                .flatten()
+                .collect(),
+            Step::Stop { .. } | Step::Launch { .. } => vec![],
+        }
+    }
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::DeleteFile { path, reason } => {
+                writeln!(f, "Delete {path:?} ({reason})")
+            }
+            Step::EditDb { path, old, new } => {
+                let change = ValueChange {
+                    key: "player_name".to_owned(),
+                    old: old.clone(),
+                    new: Some(new.clone()),
+                };
+
+                writeln!(f, "Edit {path:?}")?;
+                writeln!(f, "    {change}")
+            }
+            Step::WriteConfig { path, changes, .. } => {
+                writeln!(f, "Write {path:?}")?;
+                for change in changes {
+                    writeln!(f, "    {change}")?;
+                }
+                Ok(())
+            }
+            Step::WriteStore { store } => {
+                writeln!(f, "Write {:?}", store.path())?;
+                if let Some(legacy_path) = store.legacy_path() {
+                    writeln!(f, "    (migrated from {legacy_path:?})")?;
+                }
+                for migration in store.migrations() {
+                    let Migration { version, description, .. } = migration;
+                    writeln!(f, "    (migration {version}: {description})")?;
+                }
+                if let Some(backup_path) = store.backup_path() {
+                    writeln!(f, "    (the old config is backed up to {backup_path:?})")?;
+                }
+                if store.has_plaintext() {
+                    writeln!(f, "    (stored session keys get encrypted)")?;
+                }
+                for change in store.changes() {
+                    writeln!(f, "    {change}")?;
+                }
+                Ok(())
+            }
+            Step::Stop {
+                osu_dir,
+                close_timeout,
+            } => {
+                match osu_dir {
+                    Some(osu_dir) => writeln!(f, "Stop osu! running from {osu_dir:?}")?,
+                    None => writeln!(f, "Stop every running osu!")?,
+                }
+                if let Some(close_timeout) = close_timeout {
+                    let timeout = close_timeout.as_secs();
+                    writeln!(f, "    ask any running osu! processes to close within {timeout}s")?;
+                }
+                writeln!(f, "    kill any running osu! processes")
+            }
+            Step::Launch {
+                launcher,
+                osu_exe,
+                server,
+            } => {
+                writeln!(f, "Launch osu! on {server:?}")?;
+                writeln!(f, "    launch {osu_exe:?} {launcher}")
+            }
+        }
+    }
+}
+
+/// All the steps required to switch servers, in the order they are performed.
+#[derive(Default)]
+pub struct SwitchPlan {
+    steps: Vec<Step>,
+    /// Decisions that will be made while switching and that cannot be planned ahead.
+    notes: Vec<String>,
+    /// Whether this plan launches an osu! repair, after which the switch has to be planned again.
+    awaits_repair: bool,
+}
+
+impl SwitchPlan {
+    /// Appends a step to the end of this plan.
+    pub fn push(&mut self, step: Step) {
+        self.steps.push(step);
+    }
+
+    /// Attaches a note to this plan that is shown when printing it.
+    pub fn note(&mut self, note: impl Into<String>) {
+        self.notes.push(note.into());
+    }
+
+    /// Marks this plan as launching an osu! repair, which has to finish before switching.
+    pub fn await_repair(&mut self) {
+        self.awaits_repair = true;
+    }
+
+    /// The steps of this plan, in the order they are performed.
+    #[cfg(test)]
+    pub fn steps(&self) -> &[Step] {
+        &*self.steps
+    }
+
+    /// Whether the switch has to be planned again once the osu! repair launched by this plan
+    /// has finished.
+    pub fn awaits_repair(&self) -> bool {
+        self.awaits_repair
+    }
+
+    /// Performs every step of this plan in order, verifying each one.
+    /// If any step fails, then every file touched by this plan is restored.
+    pub fn apply(&self) -> Result<()> {
+        let transaction = Transaction::begin(self.steps.iter().flat_map(Step::paths))?;
+
+        for step in &self.steps {
+            if let Err(err) = step.apply().and_then(|_| step.verify()) {
+                println!("Failed to switch servers: {err:#}");
+                transaction.rollback();
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for SwitchPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for note in &self.notes {
+            writeln!(f, "Note: {note}")?;
+        }
+        for (idx, step) in self.steps.iter().enumerate() {
+            write!(f, "{0}. {step}", idx + 1)?;
+        }
+        if self.awaits_repair {
+            writeln!(f, "Then wait for the osu! repair to finish, and plan the switch again")?;
+        }
+        Ok(())
+    }
+}
+
+/// Edits the osu!.db to replace the username stored within.
+fn edit_db(osu_db: &Path, new_username: &str) -> Result<()> {
+    let mut db = osu_db::Listing::from_file(osu_db).context("failed to open osu!.db")?;
+    db.player_name = Some(new_username.to_owned());
+    db.save(osu_db).context("failed to write osu!.db")?;
+    Ok(())
+}
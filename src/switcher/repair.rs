@@ -0,0 +1,187 @@
+use crate::process;
+use crate::process::ProcessController;
+use color_eyre::eyre::{bail, eyre};
+use color_eyre::Result;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What to do when osu! has a pending repair (`.require_update`) while switching.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum RepairPolicy {
+    /// Cancel the repair and continue launching on the target server.
+    Launch,
+    /// Remove all credentials and let the updater repair osu!.
+    Repair,
+    /// Ask the user which of the two to do.
+    #[default]
+    Ask,
+}
+
+impl FromStr for RepairPolicy {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "launch" => Ok(Self::Launch),
+            "repair" => Ok(Self::Repair),
+            "ask" => Ok(Self::Ask),
+            _ => Err(eyre!("unknown repair policy {s:?}, expected launch, repair or ask")),
+        }
+    }
+}
+
+impl Display for RepairPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Launch => "launch",
+            Self::Repair => "repair",
+            Self::Ask => "ask",
+        })
+    }
+}
+
+/// Prompts the user whether to skip a pending osu! repair, picking the default choice if none
+/// was made before the timeout (if any). Without a terminal, the choice is read line by line
+/// from stdin instead. Returns whether the repair should be cancelled.
+pub fn prompt(timeout: Option<Duration>, default_launch: bool) -> Result<bool> {
+    let default = if default_launch { "launching" } else { "repairing" };
+
+    print!("Detected a pending osu! repair. Continue [L]aunching or allow [R]epair? ");
+    if let Some(timeout) = timeout {
+        print!("({default} in {0}s) ", timeout.as_secs());
+    }
+    std::io::stdout().flush()?;
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let choice = if std::io::stdin().is_terminal() {
+        read_key(deadline)?
+    } else {
+        read_line(deadline)
+    };
+
+    println!();
+    let launch = choice.unwrap_or_else(|| {
+        println!("No choice was made, {default} by default");
+        default_launch
+    });
+
+    if launch {
+        println!("Cancelling scheduled osu! updater repair...");
+    } else {
+        println!("Allowing osu! updater repair to continue...");
+    }
+    Ok(launch)
+}
+
+/// The time left until a deadline, if there is one.
+fn remaining(deadline: Option<Instant>) -> Option<Duration> {
+    deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+}
+
+/// Waits for an L or R key press. Returns `None` if the deadline passed.
+fn read_key(deadline: Option<Instant>) -> Result<Option<bool>> {
+    loop {
+        if let Some(remaining) = remaining(deadline)
+            && !crossterm::event::poll(remaining)?
+        {
+            return Ok(None);
+        }
+
+        let Event::Key(KeyEvent {
+            code,
+            kind: KeyEventKind::Press,
+            ..
+        }) = crossterm::event::read()?
+        else {
+            continue;
+        };
+
+        match code {
+            KeyCode::Char('l' | 'L') => return Ok(Some(true)),
+            KeyCode::Char('r' | 'R') => return Ok(Some(false)),
+            KeyCode::Esc => bail!("cancelled while asking about the pending osu! repair"),
+            _ => {}
+        }
+    }
+}
+
+/// Reads lines from stdin until one is a choice. Returns `None` if the deadline passed
+/// or stdin was closed.
+fn read_line(deadline: Option<Instant>) -> Option<bool> {
+    let (sender, receiver) = mpsc::channel();
+
+    // Reading stdin can't be interrupted, so leave the thread behind if the deadline passes
+    thread::spawn(move || {
+        for line in std::io::stdin().lines() {
+            let Ok(line) = line else { break };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    loop {
+        let line = match remaining(deadline) {
+            Some(remaining) => receiver.recv_timeout(remaining),
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match line.ok()?.trim().to_lowercase().as_str() {
+            "l" | "launch" => return Some(true),
+            "r" | "repair" => return Some(false),
+            _ => print!("Please enter L or R: "),
+        }
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Waits for an osu! repair to finish, which is once the force updater flag is gone and the
+/// updater has restarted osu!, or no osu! processes have changed for a while.
+pub fn wait(osu_dir: &Path, timeout: Duration) -> Result<()> {
+    /// How long the running osu! processes have to stay the same for the updater to be done.
+    const SETTLE_TIME: Duration = Duration::from_secs(5);
+
+    let force_update_file = osu_dir.join(".require_update");
+    let deadline = Instant::now() + timeout;
+    let controller = process::default_controller();
+    let mut processes = osu_pids(&*controller, osu_dir)?;
+    let mut settled_since = Instant::now();
+
+    println!("Waiting for osu! to finish repairing (up to {0}s)...", timeout.as_secs());
+
+    loop {
+        let current = osu_pids(&*controller, osu_dir)?;
+        if current != processes {
+            processes = current;
+            settled_since = Instant::now();
+        }
+
+        if !fs::exists(&*force_update_file).unwrap_or(true)
+            && settled_since.elapsed() >= SETTLE_TIME
+        {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            bail!(
+                "osu! did not finish repairing within {0}s, switch servers again once it has",
+                timeout.as_secs()
+            );
+        }
+
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+/// Finds the PIDs of every osu! process running from an installation, including the updater.
+fn osu_pids(controller: &dyn ProcessController, osu_dir: &Path) -> Result<Vec<u32>> {
+    let processes = controller.find_osu(Some(osu_dir))?;
+    Ok(processes.iter().map(|process| process.pid).collect())
+}
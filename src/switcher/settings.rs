@@ -0,0 +1,38 @@
+use crate::store::settings;
+use crate::store::SwitcherStore;
+use crate::switcher::lock::SwitchLock;
+use color_eyre::eyre::ContextCompat;
+use color_eyre::Result;
+use std::path::Path;
+
+/// Prints every setting along with its current value.
+pub fn list(osu_dir: &Path) -> Result<()> {
+    let store = SwitcherStore::load(osu_dir)?;
+
+    for setting in settings::ALL {
+        let value = store.setting(setting);
+        let source = if store.is_setting_set(setting) { "" } else { " (default)" };
+
+        println!("{0:<16} {value}{source}", setting.name);
+        println!("    {0}", setting.description);
+    }
+    Ok(())
+}
+
+/// Changes a setting of the switcher for an osu! installation.
+pub fn set(osu_dir: &Path, name: &str, value: &str) -> Result<()> {
+    let setting = settings::find(name).with_context(|| {
+        let names: Vec<&str> = settings::ALL.iter().map(|setting| setting.name).collect();
+        format!("unknown setting {name:?}, expected one of {0}", names.join(", "))
+    })?;
+
+    let _lock = SwitchLock::acquire(osu_dir)?;
+    let mut store = SwitcherStore::load(osu_dir)?;
+
+    store.set_setting(setting, value)?;
+    store.save()?;
+    store.verify()?;
+
+    println!("Set {name} to {value}");
+    Ok(())
+}
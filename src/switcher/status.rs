@@ -0,0 +1,77 @@
+use crate::secrets;
+use crate::store::SwitcherStore;
+use crate::switcher::read_current_auth;
+use color_eyre::eyre::Context;
+use color_eyre::Result;
+use std::fs;
+use std::path::Path;
+
+/// Prints which server and account osu! is currently configured for, along with every account
+/// stored by the switcher. Session keys are masked.
+pub fn print_status(osu_dir: &Path) -> Result<()> {
+    let osu_db = osu_dir.join("osu!.db");
+
+    println!("osu! installation:  {osu_dir:?}");
+
+    let current = read_current_auth(osu_dir)?;
+    match &current {
+        Some(auth) => {
+            println!("Current server:     {0}", auth.server);
+            println!("Current username:   {0}", auth.username);
+            println!("Session key:        {0}", secrets::mask(&*auth.password));
+        }
+        None => println!("Current server:     (osu! has not created its config yet)"),
+    }
+
+    if fs::exists(&*osu_db)? {
+        let db = osu_db::Listing::from_file(&*osu_db).context("failed to open osu!.db")?;
+        let player_name = db.player_name.unwrap_or_default();
+
+        let agreement = match &current {
+            Some(auth) if auth.username == player_name => "matches the config",
+            Some(_) => "DOES NOT match the config, the session key will be invalidated",
+            None => "no config to compare with",
+        };
+        println!("osu!.db username:   {player_name} ({agreement})");
+    } else {
+        println!("osu!.db username:   (osu!.db does not exist yet)");
+    }
+
+    let pending_repair = fs::exists(osu_dir.join(".require_update"))?;
+    println!(
+        "Pending repair:     {0}",
+        if pending_repair {
+            "yes, the next switch will ask whether to launch or repair"
+        } else {
+            "no"
+        }
+    );
+
+    println!();
+    print_accounts(&SwitcherStore::load(osu_dir)?);
+    Ok(())
+}
+
+/// Prints every account stored by the switcher, grouped by server. Session keys are masked.
+pub fn print_accounts(store: &SwitcherStore) {
+    let mut accounts = store.accounts();
+    accounts.sort_by(|a, b| a.server.cmp(&b.server));
+
+    if accounts.is_empty() {
+        println!("No stored sessions.");
+        return;
+    }
+
+    println!("Stored sessions:");
+    for account in accounts {
+        let last_used = store.last_account(&*account.server) == Some(&*account.username);
+
+        println!(
+            "  {0:<24} {1:<20} {2}{3}",
+            account.server,
+            account.username,
+            secrets::mask(&*account.password),
+            if last_used { " (last used)" } else { "" },
+        );
+    }
+}
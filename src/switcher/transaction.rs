@@ -0,0 +1,157 @@
+use color_eyre::eyre::Context;
+use color_eyre::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The original contents of a file before switching touched it.
+struct Backup {
+    path: PathBuf,
+    /// `None` if the file did not exist beforehand.
+    contents: Option<Vec<u8>>,
+}
+
+/// Snapshots of every file a switch is about to touch, so that they can all be restored
+/// to their original state if any step of the switch fails.
+pub struct Transaction {
+    backups: Vec<Backup>,
+}
+
+impl Transaction {
+    /// Takes a backup of every specified file before anything gets written.
+    pub fn begin<'a, P>(paths: P) -> Result<Self>
+    where
+        P: IntoIterator<Item=&'a Path>,
+    {
+        let mut backups: Vec<Backup> = Vec::new();
+
+        for path in paths {
+            if backups.iter().any(|backup| backup.path == path) {
+                continue;
+            }
+
+            let contents = if fs::exists(path).unwrap_or(false) {
+                let bytes = fs::read(path)
+                    .with_context(|| format!("failed to back up {path:?} before switching"))?;
+                Some(bytes)
+            } else {
+                None
+            };
+
+            backups.push(Backup {
+                path: path.to_owned(),
+                contents,
+            });
+        }
+
+        Ok(Self { backups })
+    }
+
+    /// Restores every backed up file to its original state and prints what was rolled back.
+    /// Restoring continues past failures so that as many files as possible are recovered.
+    pub fn rollback(self) {
+        println!("Rolling back all changes...");
+        let mut rolled_back = false;
+
+        for Backup { path, contents } in self.backups {
+            let result = match contents {
+                Some(contents) => {
+                    let unchanged = fs::read(&*path).is_ok_and(|current| current == contents);
+                    if unchanged {
+                        continue;
+                    }
+                    fs::write(&*path, contents).map(|_| "restored")
+                }
+                None if fs::exists(&*path).unwrap_or(false) => {
+                    fs::remove_file(&*path).map(|_| "removed")
+                }
+                None => continue,
+            };
+
+            rolled_back = true;
+            match result {
+                Ok(action) => println!("  {action} {path:?}"),
+                Err(err) => println!("  FAILED to restore {path:?}: {err}"),
+            }
+        }
+
+        if !rolled_back {
+            println!("  no files were changed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::secrets::KeyFileProvider;
+    use crate::store::{AuthDetails, SwitcherStore};
+    use crate::switcher::plan::{Step, SwitchPlan};
+    use crate::test_util::TempOsuDir;
+    use ini::Ini;
+    use std::fs;
+
+    #[test]
+    fn rolls_back_failed_plan() {
+        let osu_dir = TempOsuDir::new("rollback");
+        let config = osu_dir.join("osu!.tester.cfg");
+        let osu_db = osu_dir.join("osu!.db");
+        let legacy_store = osu_dir.join("server-account-switcher.ini");
+
+        fs::write(&*config, "Username = alice\nCredentialEndpoint = akatsuki.gg\n").unwrap();
+        osu_db::Listing {
+            version: 20250107,
+            folder_count: 0,
+            unban_date: None,
+            player_name: Some("alice".to_owned()),
+            beatmaps: Vec::new(),
+            user_permissions: 0,
+        }
+        .save(&*osu_db)
+        .unwrap();
+        fs::write(
+            &*legacy_store,
+            include_str!("../../tests/fixtures/store/v0_legacy_layout.ini"),
+        )
+        .unwrap();
+
+        let originals = [&config, &osu_db, &legacy_store].map(|path| fs::read(path).unwrap());
+
+        let mut ini = Ini::load_from_file(&*config).unwrap();
+        ini.with_general_section()
+            .set("Username", "bob")
+            .set("CredentialEndpoint", "ripple.moe");
+
+        let secrets = Box::new(KeyFileProvider::new(osu_dir.join("test.key")));
+        let mut store = SwitcherStore::load_with(&*osu_dir, secrets).unwrap();
+        store.put(&AuthDetails {
+            username: "alice".to_owned(),
+            password: "alicekey".to_owned(),
+            server: "akatsuki.gg".to_owned(),
+        });
+        let backup = store.backup_path().unwrap().to_owned();
+
+        let mut plan = SwitchPlan::default();
+        plan.push(Step::WriteConfig {
+            path: config.clone(),
+            ini,
+            changes: Vec::new(),
+        });
+        plan.push(Step::EditDb {
+            path: osu_db.clone(),
+            old: Some("alice".to_owned()),
+            new: "bob".to_owned(),
+        });
+        plan.push(Step::WriteStore { store });
+        // Fails, since the file doesn't exist
+        plan.push(Step::DeleteFile {
+            path: osu_dir.join("missing.log"),
+            reason: "test",
+        });
+
+        assert!(plan.apply().is_err());
+
+        let restored = [&config, &osu_db, &legacy_store].map(|path| fs::read(path).unwrap());
+        assert_eq!(restored, originals);
+        assert!(!fs::exists(osu_dir.join(SwitcherStore::FILE_NAME)).unwrap());
+        assert!(!fs::exists(backup).unwrap());
+    }
+}
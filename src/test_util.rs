@@ -0,0 +1,34 @@
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
+
+/// An empty temporary directory that acts as an osu! installation in tests.
+/// It is deleted again when dropped, including when the test fails.
+pub struct TempOsuDir {
+    path: PathBuf,
+}
+
+impl TempOsuDir {
+    /// Creates an empty directory, named uniquely per test and test run.
+    pub fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("osu-switcher-{name}-{0}", process::id()));
+        let _ = fs::remove_dir_all(&*path);
+        fs::create_dir_all(&*path).unwrap();
+
+        Self { path }
+    }
+}
+
+impl Deref for TempOsuDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &*self.path
+    }
+}
+
+impl Drop for TempOsuDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&*self.path);
+    }
+}
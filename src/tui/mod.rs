@@ -1,6 +1,8 @@
-use crate::osu_util::{check_osu_installation, find_osu_installation, flatten_osu_installation};
+use crate::osu_util::{check_osu_installation, flatten_osu_installation};
+use crate::platform::find_osu_installation;
 use crate::shortcuts;
 use crate::tui::input::InputState;
+use crate::uninstall::uninstall;
 use color_eyre::eyre::Context;
 use color_eyre::Result;
 use crossterm::event;
@@ -14,8 +16,13 @@ use std::path::{Path, PathBuf};
 
 mod input;
 
-pub fn start_tui() {
-    let mut app = App::default();
+/// Starts the interactive installer, which installs the switcher into the osu! directory itself
+/// if `portable` is set.
+pub fn start_tui(portable: bool) {
+    let mut app = App {
+        portable,
+        ..App::default()
+    };
 
     ratatui::run(|terminal| app.run(terminal))
         .context("app loop failed")
@@ -37,6 +44,19 @@ enum AppState {
         input: InputState,
         retrying: bool,
     },
+    SelectingAction {
+        /// The available items for this options list are:
+        /// `0`: Create shortcuts
+        /// `1`: Uninstall, keeping the stored sessions
+        /// `2`: Uninstall, and forget the stored sessions
+        items: ListState,
+    },
+    ConfirmingPurge {
+        /// The available items for this options list are:
+        /// `0`: Go back without uninstalling
+        /// `1`: Uninstall, and forget the stored sessions
+        items: ListState,
+    },
     SelectingOsuDomains {
         items: ListState,
     },
@@ -44,7 +64,9 @@ enum AppState {
         input: InputState,
         retrying: bool,
     },
-    Exiting,
+    Exiting {
+        message: String,
+    },
 }
 
 #[derive(Debug)]
@@ -58,6 +80,8 @@ struct App {
     state: AppState,
     osu_dir: Option<PathBuf>,
     osu_servers: Vec<ServerState>,
+    /// Whether to install the switcher into the osu! directory.
+    portable: bool,
 }
 
 impl App {
@@ -83,10 +107,10 @@ Press 'Ctrl+C' to forcefully exit.
         loop {
             terminal.draw(|frame| self.draw(frame))?;
 
-            if let Event::Key(key) = event::read()? {
-                if !self.update(key)? {
-                    break Ok(());
-                }
+            if let Event::Key(key) = event::read()?
+                && !self.update(key)?
+            {
+                break Ok(());
             }
         }
     }
@@ -120,7 +144,7 @@ Press 'Ctrl+C' to forcefully exit.
         }
 
         // App has finished and is waiting for any key press to exit
-        if self.state == AppState::Exiting && key.kind == KeyEventKind::Press {
+        if matches!(self.state, AppState::Exiting { .. }) && key.kind == KeyEventKind::Press {
             return Ok(false);
         }
 
@@ -135,8 +159,8 @@ Press 'Ctrl+C' to forcefully exit.
                             let path = std::mem::replace(default, PathBuf::new());
 
                             self.osu_dir = Some(path);
-                            self.state = AppState::SelectingOsuDomains {
-                                items: ListState::default(),
+                            self.state = AppState::SelectingAction {
+                                items: ListState::default().with_selected(Some(0)),
                             };
                         }
                         // Custom install path selected
@@ -157,14 +181,61 @@ Press 'Ctrl+C' to forcefully exit.
 
                     if check_osu_installation(&*osu_dir) {
                         self.osu_dir = Some(osu_dir.into_owned());
-                        self.state = AppState::SelectingOsuDomains {
-                            items: ListState::default(),
+                        self.state = AppState::SelectingAction {
+                            items: ListState::default().with_selected(Some(0)),
                         };
                     } else {
                         *retrying = true;
                     }
                 }
 
+                // User selected whether to create shortcuts or uninstall
+                AppState::SelectingAction { items } => {
+                    let osu_dir = self.osu_dir.as_deref().unwrap();
+
+                    match items.selected() {
+                        Some(0) => {
+                            // Start from the shortcuts that were already created
+                            for domain in shortcuts::created_servers(osu_dir)? {
+                                match self.osu_servers.iter_mut().find(|s| s.domain == domain) {
+                                    Some(server) => server.enabled = true,
+                                    None => self.osu_servers.push(ServerState {
+                                        domain,
+                                        enabled: true,
+                                    }),
+                                }
+                            }
+
+                            self.state = AppState::SelectingOsuDomains {
+                                items: ListState::default(),
+                            };
+                        }
+                        Some(1) => self.state = Self::uninstall(osu_dir, false)?,
+                        // Forgetting the stored sessions can't be undone, so ask first
+                        Some(2) => {
+                            self.state = AppState::ConfirmingPurge {
+                                items: ListState::default().with_selected(Some(0)),
+                            };
+                        }
+                        _ => {}
+                    }
+                }
+
+                // User confirmed whether to forget the stored sessions
+                AppState::ConfirmingPurge { items } => {
+                    let osu_dir = self.osu_dir.as_deref().unwrap();
+
+                    match items.selected() {
+                        Some(0) => {
+                            self.state = AppState::SelectingAction {
+                                items: ListState::default().with_selected(Some(2)),
+                            };
+                        }
+                        Some(1) => self.state = Self::uninstall(osu_dir, true)?,
+                        _ => {}
+                    }
+                }
+
                 // User finished inputting custom osu! server domain
                 AppState::InputtingOsuDomain { input, retrying } => {
                     if !input.text().contains(".") && input.text() != "localhost" {
@@ -199,8 +270,10 @@ Press 'Ctrl+C' to forcefully exit.
                             .filter(|server| server.enabled)
                             .map(|server| &*server.domain);
 
-                        shortcuts::install(osu_dir, servers)?;
-                        self.state = AppState::Exiting;
+                        shortcuts::install(osu_dir, servers, true, self.portable)?;
+                        self.state = AppState::Exiting {
+                            message: "Created all shortcuts! Press any key to exit...".to_owned(),
+                        };
                     }
                 }
 
@@ -212,33 +285,31 @@ Press 'Ctrl+C' to forcefully exit.
         }
 
         // Selecting osu! domains from list
-        if key.code == KeyCode::Char(' ') && key.kind == KeyEventKind::Press {
-            if let AppState::SelectingOsuDomains { items } = &self.state {
-                let selected_idx = items.selected();
-
-                if let Some(idx) = selected_idx
-                    && let Some(server) = self.osu_servers.get_mut(idx)
-                {
-                    server.enabled = !server.enabled;
-                }
-            }
+        if key.code == KeyCode::Char(' ')
+            && key.kind == KeyEventKind::Press
+            && let AppState::SelectingOsuDomains { items } = &self.state
+            && let Some(idx) = items.selected()
+            && let Some(server) = self.osu_servers.get_mut(idx)
+        {
+            server.enabled = !server.enabled;
         }
 
         // List navigation
-        if matches!(key.kind, KeyEventKind::Press | KeyEventKind::Repeat) {
-            if let AppState::SelectingOsuDirectory { items, .. }
+        if matches!(key.kind, KeyEventKind::Press | KeyEventKind::Repeat)
+            && let AppState::SelectingOsuDirectory { items, .. }
+            | AppState::SelectingAction { items }
+            | AppState::ConfirmingPurge { items }
             | AppState::SelectingOsuDomains { items } = &mut self.state
-            {
-                match key.code {
-                    KeyCode::Up => items.select_previous(),
-                    KeyCode::Down => items.select_next(),
-                    KeyCode::PageUp | KeyCode::Home => items.select_first(),
-                    KeyCode::PageDown | KeyCode::End => items.select_last(),
-                    _ => {}
-                }
-
-                return Ok(true);
+        {
+            match key.code {
+                KeyCode::Up => items.select_previous(),
+                KeyCode::Down => items.select_next(),
+                KeyCode::PageUp | KeyCode::Home => items.select_first(),
+                KeyCode::PageDown | KeyCode::End => items.select_last(),
+                _ => {}
             }
+
+            return Ok(true);
         }
 
         // Generic text input
@@ -255,6 +326,18 @@ Press 'Ctrl+C' to forcefully exit.
         Ok(true)
     }
 
+    /// Uninstalls the switcher, and returns the state that lists everything that was deleted.
+    fn uninstall(osu_dir: &Path, purge: bool) -> Result<AppState> {
+        let removed = uninstall(osu_dir, purge)?;
+        let mut message = String::from("Uninstalled osu!switcher!\n\n");
+        for path in removed {
+            message.push_str(&*format!("Deleted {path:?}\n"));
+        }
+        message.push_str("\nPress any key to exit...");
+
+        Ok(AppState::Exiting { message })
+    }
+
     /// Renders the current TUI state to the terminal.
     fn draw(&mut self, frame: &mut Frame) {
         let [banner_area, area] = Layout::default()
@@ -292,6 +375,10 @@ Press 'Ctrl+C' to forcefully exit.
                     None
                 },
             ),
+            AppState::SelectingAction { items } => Self::draw_action_picker(frame, area, items),
+            AppState::ConfirmingPurge { items } => {
+                Self::draw_purge_confirmation(frame, area, items);
+            }
             AppState::SelectingOsuDomains { items } => {
                 Self::draw_domains_picker(frame, area, &*self.osu_servers, items);
             }
@@ -299,14 +386,14 @@ Press 'Ctrl+C' to forcefully exit.
                 frame,
                 area,
                 input,
-                " Enter new osu! private server domain (eg. 'akatsuki.pw') ",
+                " Enter new osu! private server domain (eg. 'akatsuki.gg') ",
                 if *retrying {
                     Some("Invalid domain! Please try again.")
                 } else {
                     None
                 },
             ),
-            AppState::Exiting => Self::draw_exiting(frame, area),
+            AppState::Exiting { message } => Self::draw_exiting(frame, area, message),
         };
     }
 
@@ -355,6 +442,55 @@ Press 'Ctrl+C' to forcefully exit.
         frame.render_stateful_widget(options, area, list);
     }
 
+    fn draw_action_picker(frame: &mut Frame, area: Rect, list: &mut ListState) {
+        let items = [
+            Span::raw("Create shortcuts for osu! private servers").green().bold(),
+            Span::raw("Uninstall osu!switcher (keeps stored sessions)"),
+            Span::raw("Uninstall osu!switcher and forget all stored sessions").red(),
+        ];
+
+        let options = List::new(items)
+            .style(Color::White)
+            .highlight_style(Modifier::REVERSED)
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always)
+            .block(
+                Self::block_border()
+                    .padding(Padding::vertical(1))
+                    .title(" What do you want to do? "),
+            );
+
+        let area = area
+            .resize(Size::new(area.width, 7))
+            .centered_vertically(Constraint::Length(7));
+
+        frame.render_stateful_widget(options, area, list);
+    }
+
+    fn draw_purge_confirmation(frame: &mut Frame, area: Rect, list: &mut ListState) {
+        let items = [
+            Span::raw("No, go back").green().bold(),
+            Span::raw("Yes, uninstall and forget all stored sessions").red(),
+        ];
+
+        let options = List::new(items)
+            .style(Color::White)
+            .highlight_style(Modifier::REVERSED)
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always)
+            .block(
+                Self::block_border()
+                    .padding(Padding::vertical(1))
+                    .title(" Forget all stored sessions? This can't be undone! "),
+            );
+
+        let area = area
+            .resize(Size::new(area.width, 6))
+            .centered_vertically(Constraint::Length(6));
+
+        frame.render_stateful_widget(options, area, list);
+    }
+
     fn draw_domains_picker(
         frame: &mut Frame,
         area: Rect,
@@ -434,10 +570,11 @@ Press 'Ctrl+C' to forcefully exit.
             .border_style(Style::new().gray().dim())
     }
 
-    fn draw_exiting(frame: &mut Frame, area: Rect) {
-        let text = Paragraph::new("Created all shortcuts! Press any key to exit...")
+    fn draw_exiting(frame: &mut Frame, area: Rect, message: &str) {
+        let text = Paragraph::new(message)
             .green()
-            .centered();
+            .centered()
+            .wrap(Wrap { trim: false });
 
         frame.render_widget(text, area);
     }
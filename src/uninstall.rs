@@ -0,0 +1,90 @@
+use crate::history;
+use crate::platform;
+use crate::platform::{PORTABLE_FILE_NAME, SWITCHER_EXE};
+use crate::secrets::KEY_FILE_NAME;
+use crate::shortcuts;
+use crate::shortcuts::manifest::ShortcutManifest;
+use crate::store::SwitcherStore;
+use crate::switcher::lock::SwitchLock;
+use color_eyre::eyre::Context;
+use color_eyre::Result;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+/// Removes everything [shortcuts::install] created: the shortcuts, the server icons and the
+/// installed switcher. With `purge`, the stored sessions, switch history and encryption key are
+/// removed as well. Returns every path that was deleted.
+pub fn uninstall(osu_dir: &Path, purge: bool) -> Result<Vec<PathBuf>> {
+    let install_dir = platform::install_dir(osu_dir)?;
+    let manifest = ShortcutManifest::load(&*install_dir)?;
+    let mut removed = Vec::new();
+
+    // Shortcuts created before the manifest existed are only found by their name
+    let recorded = manifest.shortcuts.iter().map(|shortcut| shortcut.path.clone());
+    let found = shortcuts::find_shortcuts()?.into_iter().map(|(shortcut, _)| shortcut);
+    for shortcut in recorded.chain(found) {
+        remove_file(&*shortcut, &mut removed)?;
+    }
+
+    for icon in shortcuts::icon_paths(osu_dir)? {
+        remove_file(&*icon, &mut removed)?;
+    }
+    remove_empty_dir(&*osu_dir.join("icons"), &mut removed)?;
+
+    if purge {
+        // Don't pull the store out from under a switch that is still running
+        let _lock = SwitchLock::acquire(osu_dir)?;
+
+        for file in SwitcherStore::files(osu_dir)? {
+            remove_file(&*file, &mut removed)?;
+        }
+        remove_file(&*osu_dir.join(history::FILE_NAME), &mut removed)?;
+        // Switches waiting on the lock notice that it was deleted, and create a new one
+        remove_file(&*osu_dir.join(SwitchLock::FILE_NAME), &mut removed)?;
+    }
+
+    let this_exe = env::current_exe().ok();
+    let installed_exe = install_dir.join(SWITCHER_EXE);
+
+    // Windows can't delete the executable of a running process
+    if cfg!(windows) && this_exe.as_deref() == Some(&*installed_exe) {
+        println!("Delete {installed_exe:?} after exiting to finish uninstalling");
+    } else {
+        remove_file(&*installed_exe, &mut removed)?;
+    }
+    remove_file(&*install_dir.join(shortcuts::README_FILE_NAME), &mut removed)?;
+    remove_file(&*install_dir.join(shortcuts::VERSION_FILE_NAME), &mut removed)?;
+    remove_file(&*install_dir.join(ShortcutManifest::FILE_NAME), &mut removed)?;
+
+    if purge {
+        remove_file(&*install_dir.join(KEY_FILE_NAME), &mut removed)?;
+        // Without the key, stored sessions are no longer kept next to the game
+        remove_file(&*install_dir.join(PORTABLE_FILE_NAME), &mut removed)?;
+    }
+    remove_empty_dir(&*install_dir, &mut removed)?;
+
+    Ok(removed)
+}
+
+/// Deletes a file if it exists, and records it as removed.
+fn remove_file(path: &Path, removed: &mut Vec<PathBuf>) -> Result<()> {
+    if !fs::exists(path).unwrap_or(false) {
+        return Ok(());
+    }
+
+    fs::remove_file(path).with_context(|| format!("failed to delete {path:?}"))?;
+    removed.push(path.to_owned());
+    Ok(())
+}
+
+/// Deletes a directory if it exists and nothing else is left inside it.
+fn remove_empty_dir(path: &Path, removed: &mut Vec<PathBuf>) -> Result<()> {
+    let is_empty = fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none());
+    if !is_empty {
+        return Ok(());
+    }
+
+    fs::remove_dir(path).with_context(|| format!("failed to delete {path:?}"))?;
+    removed.push(path.to_owned());
+    Ok(())
+}